@@ -0,0 +1,160 @@
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use stateloop::{
+    error::AppError,
+    winit::{
+        event::Event as LoopEvent,
+        event_loop::{ControlFlow, EventLoop, EventLoopProxy},
+        platform::run_return::EventLoopExtRunReturn,
+    },
+};
+
+pub use stateloop::winit::event::WindowEvent as Event;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Redraw {
+    /// Every tick, whether or not anything has changed
+    #[default]
+    Continuous,
+    /// Only after input, while the camera moves or as chunks arrive, sleeping otherwise
+    OnDemand,
+}
+
+/// Sent from other threads to wake the event loop, such as when a chunk is ready.
+#[derive(Debug, Copy, Clone)]
+pub struct Wake;
+
+pub enum Action {
+    Continue,
+    Quit,
+}
+
+pub trait Handler {
+    type Error;
+
+    fn handle_event(&mut self, event: Event) -> Action;
+    /// An error stops the event loop, and is returned from `App::run`.
+    fn handle_tick(&mut self) -> Result<(), Self::Error>;
+    fn handle_render(&self);
+
+    /// Whether ticks are still needed without any new events, such as while the camera is
+    /// moving or uploads are in progress.
+    fn is_busy(&self) -> bool;
+}
+
+/// Ticks at a fixed rate like stateloop's app, but can sleep between events instead, as
+/// long as other threads wake it through its proxy.
+pub struct App<D, W> {
+    event_loop: EventLoop<Wake>,
+    data: Data<D, W>,
+}
+
+pub struct Data<D, W> {
+    window: W,
+    pub data: D,
+}
+
+impl<D, W> App<D, W> {
+    pub fn new<WindowInit, DataInit, E1, E2>(
+        f: WindowInit,
+        g: DataInit,
+    ) -> Result<Self, AppError<E1, E2>>
+    where
+        WindowInit: FnOnce(&EventLoop<Wake>) -> Result<W, E1>,
+        DataInit: FnOnce(&W, EventLoopProxy<Wake>) -> Result<D, E2>,
+    {
+        let event_loop = EventLoop::with_user_event();
+        let window = f(&event_loop).map_err(AppError::WindowError)?;
+        let data = g(&window, event_loop.create_proxy()).map_err(AppError::DataError)?;
+
+        Ok(Self {
+            event_loop,
+            data: Data { window, data },
+        })
+    }
+
+    pub fn run(
+        &mut self,
+        tick_rate: u32,
+        redraw: Redraw,
+    ) -> Result<(), <Data<D, W> as Handler>::Error>
+    where
+        Data<D, W>: Handler,
+    {
+        let tick = Duration::from_secs(1) / tick_rate;
+        let data = &mut self.data;
+
+        let mut next_tick = Instant::now();
+        let mut sleeping = false;
+        let mut quit = false;
+        let mut error = None;
+
+        // Anything that happens is followed by at least one tick and one frame
+        let mut tick_owed = true;
+        let mut frame_owed = true;
+
+        self.event_loop.run_return(|event, _, flow| {
+            match event {
+                LoopEvent::WindowEvent { event, .. } if !quit => {
+                    if let Action::Quit = data.handle_event(event) {
+                        quit = true;
+                    }
+
+                    tick_owed = true;
+                    frame_owed = true;
+                }
+                LoopEvent::UserEvent(Wake) => tick_owed = true,
+                LoopEvent::MainEventsCleared if !quit => {
+                    let now = Instant::now();
+
+                    // Carry on from now after sleeping, rather than catching up
+                    if sleeping {
+                        next_tick = next_tick.max(now);
+                        sleeping = false;
+                    }
+
+                    while next_tick <= now {
+                        if let Err(e) = data.handle_tick() {
+                            error = Some(e);
+                            quit = true;
+                            break;
+                        }
+
+                        next_tick += tick;
+                        tick_owed = false;
+                        frame_owed = true;
+                    }
+
+                    if frame_owed && !quit {
+                        data.handle_render();
+                        frame_owed = false;
+                    }
+
+                    *flow = match redraw {
+                        Redraw::OnDemand if !tick_owed && !data.is_busy() => {
+                            sleeping = true;
+                            ControlFlow::Wait
+                        }
+                        _ => ControlFlow::WaitUntil(next_tick),
+                    };
+                }
+                _ => (),
+            }
+
+            if quit {
+                *flow = ControlFlow::Exit;
+            }
+        });
+
+        error.map_or(Ok(()), Err)
+    }
+}
+
+impl<D, W> Data<D, W> {
+    pub fn window(&self) -> &W {
+        &self.window
+    }
+}
@@ -0,0 +1,61 @@
+use std::time::Instant;
+
+use crate::{
+    settings::{BenchArgs, Settings},
+    world::{ChunkKey, HeapTexels, Latency, World, WorldConfig, WorldDefinition, WorldError},
+};
+
+/// Generates a square of chunks as fast as the workers allow, then prints how long each
+/// chunk took and the overall rate.
+pub fn bench(
+    settings: &Settings,
+    config: WorldConfig,
+    definition: WorldDefinition,
+    args: &BenchArgs,
+) -> Result<(), WorldError> {
+    let world = World::new(
+        config,
+        settings.seed,
+        definition,
+        settings.workers,
+        settings.queue_limits,
+        HeapTexels,
+        || (),
+    )?;
+    let count = args.size as usize * args.size as usize;
+
+    println!(
+        "Generating {0}x{0} chunks with seed {1}, preset {2} and {3} workers",
+        args.size, settings.seed, definition, settings.workers
+    );
+
+    let start = Instant::now();
+
+    let size = args.size as i64;
+    let keys = (0..size).flat_map(|y| (0..size).map(move |x| ChunkKey::new(x, y)));
+    let mut timings = Vec::with_capacity(count);
+
+    world.generate_all(keys, |generated| {
+        timings.push(generated.chunk.timing);
+        Ok::<_, WorldError>(())
+    })?;
+
+    let elapsed = start.elapsed();
+
+    println!(
+        "Generated {} chunks in {:.2} s ({:.1} chunks/s)",
+        count,
+        elapsed.as_secs_f64(),
+        count as f64 / elapsed.as_secs_f64()
+    );
+    println!(
+        "Generation: {}",
+        Latency::new(timings.iter().map(|timing| timing.generation))
+    );
+    println!(
+        "Queue wait: {}",
+        Latency::new(timings.iter().map(|timing| timing.queued))
+    );
+
+    Ok(())
+}
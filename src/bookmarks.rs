@@ -0,0 +1,188 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::coords::TilePos;
+
+pub struct Bookmark {
+    pub name: String,
+    pub position: TilePos,
+}
+
+/// Named positions for one seed, saved alongside those of every other seed in a
+/// plain text file of `seed x y name` lines.
+pub struct Bookmarks {
+    path: PathBuf,
+    seed: u64,
+    entries: Vec<Bookmark>,
+    other_seeds: Vec<String>,
+    current: Option<usize>,
+}
+
+impl Bookmarks {
+    /// Loads the bookmarks for `seed`, starting empty if the file does not exist yet.
+    pub fn load<P: AsRef<Path>>(path: P, seed: u64) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+
+        let mut entries = Vec::new();
+        let mut other_seeds = Vec::new();
+
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            match parse_line(line) {
+                Some((line_seed, bookmark)) if line_seed == seed => entries.push(bookmark),
+                Some(_) => other_seeds.push(line.to_string()),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("malformed bookmark '{line}'"),
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            path,
+            seed,
+            entries,
+            other_seeds,
+            current: None,
+        })
+    }
+
+    /// Adds a bookmark, replacing any with the same name, and saves the file.
+    pub fn add(&mut self, name: String, position: TilePos) -> io::Result<()> {
+        match self.entries.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => entry.position = position,
+            None => self.entries.push(Bookmark { name, position }),
+        }
+
+        self.save()
+    }
+
+    /// The bookmark after the one last returned, wrapping around.
+    pub fn next(&mut self) -> Option<&Bookmark> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let index = self
+            .current
+            .map_or(0, |current| (current + 1) % self.entries.len());
+
+        self.current = Some(index);
+        self.entries.get(index)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let mut contents = String::new();
+
+        for line in &self.other_seeds {
+            contents.push_str(line);
+            contents.push('\n');
+        }
+
+        for entry in &self.entries {
+            contents.push_str(&format!(
+                "{} {} {} {}\n",
+                self.seed, entry.position.x, entry.position.y, entry.name
+            ));
+        }
+
+        fs::write(&self.path, contents)
+    }
+}
+
+fn parse_line(line: &str) -> Option<(u64, Bookmark)> {
+    let mut parts = line.trim().splitn(4, ' ');
+
+    let seed = parts.next()?.parse().ok()?;
+    let x = parts.next()?.parse().ok()?;
+    let y = parts.next()?.parse().ok()?;
+    let name = parts.next()?.to_string();
+
+    Some((
+        seed,
+        Bookmark {
+            name,
+            position: TilePos::new(x, y),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(bookmarks: &mut Bookmarks) -> Vec<String> {
+        (0..bookmarks.entries.len())
+            .filter_map(|_| bookmarks.next().map(|bookmark| bookmark.name.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn missing_file_starts_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut bookmarks = Bookmarks::load(dir.path().join("bookmarks.txt"), 1).unwrap();
+
+        assert!(bookmarks.next().is_none());
+    }
+
+    #[test]
+    fn bookmarks_are_saved_per_seed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bookmarks.txt");
+
+        let mut first = Bookmarks::load(&path, 1).unwrap();
+        first.add("home".into(), TilePos::new(1, 2)).unwrap();
+        first
+            .add("far away".into(), TilePos::new(-300, 40))
+            .unwrap();
+
+        let mut second = Bookmarks::load(&path, 2).unwrap();
+        assert!(second.next().is_none());
+        second.add("home".into(), TilePos::new(5, 5)).unwrap();
+
+        // Saving one seed's bookmarks keeps every other seed's
+        let mut first = Bookmarks::load(&path, 1).unwrap();
+        assert_eq!(names(&mut first), ["home", "far away"]);
+        assert_eq!(first.next().unwrap().position, TilePos::new(1, 2));
+        assert_eq!(first.next().unwrap().position, TilePos::new(-300, 40));
+
+        let mut second = Bookmarks::load(&path, 2).unwrap();
+        assert_eq!(second.next().unwrap().position, TilePos::new(5, 5));
+    }
+
+    #[test]
+    fn adding_an_existing_name_moves_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bookmarks.txt");
+
+        let mut bookmarks = Bookmarks::load(&path, 1).unwrap();
+        bookmarks.add("home".into(), TilePos::new(1, 2)).unwrap();
+        bookmarks.add("home".into(), TilePos::new(3, 4)).unwrap();
+
+        let mut bookmarks = Bookmarks::load(&path, 1).unwrap();
+        assert_eq!(names(&mut bookmarks), ["home"]);
+        assert_eq!(bookmarks.next().unwrap().position, TilePos::new(3, 4));
+    }
+
+    #[test]
+    fn malformed_files_are_not_loaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bookmarks.txt");
+
+        for contents in ["1 2 home", "x 1 2 home", "1 2 y home"] {
+            fs::write(&path, contents).unwrap();
+
+            let error = Bookmarks::load(&path, 1).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{contents}");
+        }
+    }
+}
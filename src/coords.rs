@@ -0,0 +1,244 @@
+use crate::world::ChunkKey;
+
+/// The furthest tile that can be gone to. World positions lose precision much beyond it.
+pub const MAX_TILE: i64 = 1 << 40;
+/// The furthest chunk that can be gone to, whose origin fits in a tile position whatever
+/// the chunk size.
+pub const MAX_CHUNK: i64 = 1 << 24;
+
+/// A position on the window in logical pixels, measured from the top-left corner.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ScreenPos {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// A continuous position in world space, measured in tiles.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WorldPos {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct TilePos {
+    pub x: i64,
+    pub y: i64,
+}
+
+/// Inclusive of both corners.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ChunkRange {
+    pub min: ChunkKey,
+    pub max: ChunkKey,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera {
+    pub centre: WorldPos,
+    /// Logical pixels per tile.
+    pub zoom: f64,
+    /// Window size in logical pixels.
+    pub viewport: (u32, u32),
+}
+
+impl ScreenPos {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
+impl WorldPos {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    pub fn tile(self) -> TilePos {
+        TilePos::new(self.x.floor() as i64, self.y.floor() as i64)
+    }
+}
+
+impl TilePos {
+    pub fn new(x: i64, y: i64) -> Self {
+        Self { x, y }
+    }
+
+    pub fn chunk_origin(key: ChunkKey, chunk_size: u32) -> Self {
+        let chunk_size = chunk_size as i64;
+        Self::new(key.x * chunk_size, key.y * chunk_size)
+    }
+
+    pub fn chunk(self, chunk_size: u32) -> ChunkKey {
+        let chunk_size = chunk_size as i64;
+        ChunkKey::new(self.x.div_euclid(chunk_size), self.y.div_euclid(chunk_size))
+    }
+
+    pub fn within_chunk(self, chunk_size: u32) -> (u32, u32) {
+        let chunk_size = chunk_size as i64;
+        (
+            self.x.rem_euclid(chunk_size) as u32,
+            self.y.rem_euclid(chunk_size) as u32,
+        )
+    }
+
+    pub fn world(self) -> WorldPos {
+        WorldPos::new(self.x as f64, self.y as f64)
+    }
+}
+
+impl ChunkRange {
+    pub fn expand(self, margin: i64) -> Self {
+        Self {
+            min: ChunkKey::new(self.min.x - margin, self.min.y - margin),
+            max: ChunkKey::new(self.max.x + margin, self.max.y + margin),
+        }
+    }
+
+    pub fn contains(self, key: ChunkKey) -> bool {
+        (self.min.x..=self.max.x).contains(&key.x) && (self.min.y..=self.max.y).contains(&key.y)
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = ChunkKey> {
+        (self.min.x..=self.max.x)
+            .flat_map(move |x| (self.min.y..=self.max.y).map(move |y| ChunkKey::new(x, y)))
+    }
+}
+
+impl Camera {
+    pub fn new(centre: WorldPos, zoom: f64, viewport: (u32, u32)) -> Self {
+        Self {
+            centre,
+            zoom,
+            viewport,
+        }
+    }
+
+    pub fn screen_to_world(&self, pos: ScreenPos) -> WorldPos {
+        WorldPos::new(
+            self.centre.x + (pos.x - self.viewport.0 as f64 / 2.0) / self.zoom,
+            self.centre.y + (pos.y - self.viewport.1 as f64 / 2.0) / self.zoom,
+        )
+    }
+
+    pub fn world_to_screen(&self, pos: WorldPos) -> ScreenPos {
+        ScreenPos::new(
+            (pos.x - self.centre.x) * self.zoom + self.viewport.0 as f64 / 2.0,
+            (pos.y - self.centre.y) * self.zoom + self.viewport.1 as f64 / 2.0,
+        )
+    }
+
+    /// The chunks overlapping the viewport, or `None` if the viewport is empty.
+    pub fn visible_chunks(&self, chunk_size: u32) -> Option<ChunkRange> {
+        if self.viewport.0 == 0 || self.viewport.1 == 0 {
+            return None;
+        }
+
+        let top_left = self.screen_to_world(ScreenPos::new(0.0, 0.0));
+        let bottom_right = self.screen_to_world(ScreenPos::new(
+            self.viewport.0 as f64,
+            self.viewport.1 as f64,
+        ));
+
+        // The viewport covers [top_left, bottom_right), so the last tile it touches
+        // is the one before the ceiling of its far edge.
+        let first = top_left.tile();
+        let last = TilePos::new(
+            bottom_right.x.ceil() as i64 - 1,
+            bottom_right.y.ceil() as i64 - 1,
+        );
+
+        Some(ChunkRange {
+            min: first.chunk(chunk_size),
+            max: last.chunk(chunk_size),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    // Power of two zoom levels and dyadic centres keep every conversion exact in f64,
+    // so the properties can compare against the integer definitions without tolerances.
+    fn zoom() -> impl Strategy<Value = f64> {
+        prop::sample::select(vec![0.125, 0.25, 0.5, 1.0, 2.0, 4.0])
+    }
+
+    fn camera() -> impl Strategy<Value = Camera> {
+        (
+            -1_000_000i64..1_000_000,
+            -1_000_000i64..1_000_000,
+            zoom(),
+            0u32..4000,
+            0u32..4000,
+        )
+            .prop_map(|(x, y, zoom, width, height)| {
+                Camera::new(
+                    WorldPos::new(x as f64 / 8.0, y as f64 / 8.0),
+                    zoom,
+                    (width, height),
+                )
+            })
+    }
+
+    fn chunk_size() -> impl Strategy<Value = u32> {
+        prop::sample::select(vec![1, 16, 64, 300, 512])
+    }
+
+    fn overlaps(min: f64, max: f64, start: f64, end: f64) -> bool {
+        start < max && end > min
+    }
+
+    proptest! {
+        #[test]
+        fn tile_chunk_round_trip(x in any::<i32>(), y in any::<i32>(), size in chunk_size()) {
+            let tile = TilePos::new(x as i64, y as i64);
+            let key = tile.chunk(size);
+            let origin = TilePos::chunk_origin(key, size);
+            let (dx, dy) = tile.within_chunk(size);
+
+            prop_assert!(dx < size && dy < size);
+            prop_assert_eq!(TilePos::new(origin.x + dx as i64, origin.y + dy as i64), tile);
+        }
+
+        #[test]
+        fn world_tile_is_floor(x in -1_000_000i64..1_000_000, y in -1_000_000i64..1_000_000) {
+            let pos = WorldPos::new(x as f64 / 8.0, y as f64 / 8.0);
+            let tile = pos.tile();
+
+            prop_assert!(tile.world().x <= pos.x && pos.x < tile.world().x + 1.0);
+            prop_assert!(tile.world().y <= pos.y && pos.y < tile.world().y + 1.0);
+        }
+
+        #[test]
+        fn screen_world_round_trip(camera in camera(), x in 0u32..4000, y in 0u32..4000) {
+            let screen = ScreenPos::new(x as f64, y as f64);
+            prop_assert_eq!(camera.world_to_screen(camera.screen_to_world(screen)), screen);
+        }
+
+        #[test]
+        fn visible_chunks_are_exact(camera in camera(), size in chunk_size()) {
+            let viewport = (camera.viewport.0 as f64, camera.viewport.1 as f64);
+            let origin = |x, y| camera.world_to_screen(TilePos::chunk_origin(ChunkKey::new(x, y), size).world());
+
+            // Visibility is separable, so checking the edges of each axis covers every chunk
+            let visible_x = |x| overlaps(0.0, viewport.0, origin(x, 0).x, origin(x + 1, 0).x);
+            let visible_y = |y| overlaps(0.0, viewport.1, origin(0, y).y, origin(0, y + 1).y);
+
+            match camera.visible_chunks(size) {
+                None => prop_assert!(camera.viewport.0 == 0 || camera.viewport.1 == 0),
+                Some(range) => {
+                    for x in [range.min.x - 1, range.min.x, range.max.x, range.max.x + 1] {
+                        prop_assert_eq!((range.min.x..=range.max.x).contains(&x), visible_x(x));
+                    }
+
+                    for y in [range.min.y - 1, range.min.y, range.max.y, range.max.y + 1] {
+                        prop_assert_eq!((range.min.y..=range.max.y).contains(&y), visible_y(y));
+                    }
+                }
+            }
+        }
+    }
+}
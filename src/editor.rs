@@ -0,0 +1,330 @@
+use std::{
+    ops::RangeInclusive,
+    time::{Duration, Instant},
+};
+
+use egui::{Align2, Color32, ComboBox, DragValue, Slider, TexturesDelta, Window};
+use stateloop::winit::event::{MouseScrollDelta, VirtualKeyCode};
+
+use crate::{
+    renderer::GuiBatch,
+    ui::Ui,
+    world::{Colour, NoiseLayer, TileKind, WorldDefinition},
+};
+
+const PANEL_WIDTH: f32 = 300.0;
+/// Clear of the minimap.
+const PANEL_OFFSET: [f32; 2] = [-10.0, 220.0];
+const STEP_RANGE: RangeInclusive<f64> = 0.0005..=0.2;
+const WEIGHT_RANGE: RangeInclusive<u32> = 1..=10;
+const THRESHOLD_RANGE: RangeInclusive<f64> = -1.0..=1.0;
+/// How long an edit is left alone before the world is regenerated with it, so dragging a
+/// slider does not regenerate on every tick.
+const APPLY_DELAY: Duration = Duration::from_millis(200);
+
+#[derive(Debug, PartialEq)]
+pub enum EditorAction {
+    Apply(WorldDefinition),
+    /// Regenerate with the definition if it differs, then write it out to a file.
+    Save(WorldDefinition),
+}
+
+/// A panel for changing the definition the world is generated with.
+pub struct Editor {
+    visible: bool,
+    /// Follows the world's definition, apart from while an edit leaves it invalid.
+    draft: WorldDefinition,
+    valid: WorldDefinition,
+    /// Why the draft has not been applied.
+    error: Option<String>,
+    /// When the draft last changed, while it is valid but not yet applied.
+    pending: Option<Instant>,
+    ui: Ui,
+    batch: GuiBatch,
+}
+
+impl Editor {
+    pub fn new(definition: WorldDefinition) -> Self {
+        Self {
+            visible: false,
+            draft: definition,
+            valid: definition,
+            error: None,
+            pending: None,
+            ui: Ui::default(),
+            batch: GuiBatch::default(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Starts editing from `definition`, dropping any unapplied edits.
+    pub fn set_definition(&mut self, definition: WorldDefinition) {
+        self.draft = definition;
+        self.valid = definition;
+        self.error = None;
+        self.pending = None;
+    }
+
+    pub fn cursor_moved(&mut self, position: [f32; 2]) {
+        self.ui.cursor_moved(position);
+    }
+
+    pub fn cursor_left(&mut self) {
+        self.ui.cursor_left();
+    }
+
+    pub fn mouse_button(&mut self, down: bool) {
+        self.ui.button(down);
+    }
+
+    pub fn scroll(&mut self, delta: MouseScrollDelta) {
+        self.ui.scroll(delta);
+    }
+
+    pub fn text(&mut self, c: char) {
+        self.ui.text(c);
+    }
+
+    pub fn key(&mut self, code: VirtualKeyCode) {
+        self.ui.key(code);
+    }
+
+    /// Whether the mouse is being used by the panel, so should be hidden from the viewer.
+    pub fn wants_mouse(&self) -> bool {
+        self.visible && self.ui.wants_mouse()
+    }
+
+    /// Whether a value is being typed in, so keys should go to the panel.
+    pub fn wants_keyboard(&self) -> bool {
+        self.visible && self.ui.wants_keyboard()
+    }
+
+    /// Whether there is an edit waiting to be applied, or the panel is animating.
+    pub fn is_busy(&self) -> bool {
+        self.pending.is_some() || (self.visible && self.ui.wants_repaint())
+    }
+
+    /// Lays the panel out over a window `size` logical pixels across, acting on any input
+    /// since the last tick.
+    pub fn tick(
+        &mut self,
+        size: [f32; 2],
+        pixels_per_point: f32,
+        now: Instant,
+    ) -> Option<EditorAction> {
+        if !self.visible {
+            self.ui.clear();
+            self.batch = GuiBatch::default();
+            return self.due(now);
+        }
+
+        let mut draft = self.draft;
+        let mut changed = false;
+        let mut save = false;
+        let error = self.error.clone();
+
+        self.batch = self.ui.run(size, pixels_per_point, |ctx| {
+            Window::new("World")
+                .anchor(Align2::RIGHT_TOP, PANEL_OFFSET)
+                .default_width(PANEL_WIDTH)
+                .resizable(false)
+                .vscroll(true)
+                .show(ctx, |ui| {
+                    ui.label(format!("Preset: {}", draft));
+
+                    for (name, layer) in
+                        NoiseLayer::NAMES.into_iter().zip(&mut draft.terrain.layers)
+                    {
+                        ui.heading(format!("Layer: {}", name));
+                        changed |= ui
+                            .add(
+                                Slider::new(&mut layer.step, STEP_RANGE)
+                                    .logarithmic(true)
+                                    .text("Step"),
+                            )
+                            .changed();
+                        changed |= ui
+                            .add(Slider::new(&mut layer.weight, WEIGHT_RANGE).text("Weight"))
+                            .changed();
+                        changed |= ui
+                            .add(DragValue::new(&mut layer.seed).prefix("Seed: "))
+                            .changed();
+                    }
+
+                    ui.heading("Thresholds");
+
+                    for (kind, threshold) in &mut draft.terrain.thresholds {
+                        changed |= ui
+                            .add(Slider::new(threshold, THRESHOLD_RANGE).text(kind.name()))
+                            .changed();
+                    }
+
+                    ComboBox::from_label("Above the thresholds")
+                        .selected_text(draft.terrain.peak.name())
+                        .show_ui(ui, |ui| {
+                            for kind in TileKind::ALL {
+                                let peak = &mut draft.terrain.peak;
+                                changed |= ui.selectable_value(peak, kind, kind.name()).changed();
+                            }
+                        });
+
+                    ui.heading("Colours");
+
+                    for kind in TileKind::ALL {
+                        let mut rgb = draft.terrain.palette.colour(kind).rgb();
+
+                        ui.horizontal(|ui| {
+                            if ui.color_edit_button_srgb(&mut rgb).changed() {
+                                let [r, g, b] = rgb;
+                                draft.terrain.palette.set_colour(kind, Colour::new(r, g, b));
+                                changed = true;
+                            }
+
+                            ui.label(kind.name());
+                        });
+                    }
+
+                    if let Some(error) = &error {
+                        ui.colored_label(Color32::LIGHT_RED, error);
+                    }
+
+                    save = ui.button("Save").clicked();
+                });
+        });
+
+        if changed {
+            self.edit(draft, now);
+        }
+
+        if save {
+            self.pending = None;
+            return Some(EditorAction::Save(self.valid));
+        }
+
+        self.due(now)
+    }
+
+    /// The panel as laid out on the last tick, empty while hidden.
+    pub fn batch(&self) -> &GuiBatch {
+        &self.batch
+    }
+
+    pub fn take_textures(&mut self) -> TexturesDelta {
+        self.ui.take_textures()
+    }
+
+    fn edit(&mut self, draft: WorldDefinition, now: Instant) {
+        self.draft = draft;
+
+        match draft.validate() {
+            Ok(()) => {
+                self.valid = draft;
+                self.error = None;
+                self.pending = Some(now);
+            }
+            Err(e) => {
+                self.error = Some(e.to_string());
+                self.pending = None;
+            }
+        }
+    }
+
+    /// The edit to apply, once it has been left alone for long enough.
+    fn due(&mut self, now: Instant) -> Option<EditorAction> {
+        self.pending
+            .filter(|&since| now.saturating_duration_since(since) >= APPLY_DELAY)
+            .map(|_| {
+                self.pending = None;
+                EditorAction::Apply(self.valid)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::Preset;
+
+    const SIZE: [f32; 2] = [1280.0, 720.0];
+
+    fn edited(step: f64) -> WorldDefinition {
+        let mut definition = WorldDefinition::from(Preset::Default);
+        definition.terrain.layers[0].step = step;
+        definition
+    }
+
+    fn after(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn applies_once_edits_settle() {
+        let mut editor = Editor::new(Preset::Default.into());
+        let start = Instant::now();
+
+        // As if dragging a slider, one edit every tick
+        for (tick, step) in [0.01, 0.02, 0.03].into_iter().enumerate() {
+            let now = after(start, tick as u64 * 50);
+            editor.edit(edited(step), now);
+            assert_eq!(editor.tick(SIZE, 1.0, now), None);
+        }
+
+        assert!(editor.is_busy());
+        assert_eq!(editor.tick(SIZE, 1.0, after(start, 299)), None);
+        assert_eq!(
+            editor.tick(SIZE, 1.0, after(start, 300)),
+            Some(EditorAction::Apply(edited(0.03)))
+        );
+
+        assert!(!editor.is_busy());
+        assert_eq!(editor.tick(SIZE, 1.0, after(start, 1000)), None);
+    }
+
+    #[test]
+    fn invalid_edits_are_not_applied() {
+        let mut editor = Editor::new(Preset::Default.into());
+        let start = Instant::now();
+
+        editor.edit(edited(0.01), start);
+        editor.edit(edited(-1.0), after(start, 50));
+
+        assert!(editor.error.is_some());
+        assert_eq!(editor.tick(SIZE, 1.0, after(start, 1000)), None);
+        assert_eq!(editor.valid, edited(0.01));
+    }
+
+    #[test]
+    fn reconfiguring_drops_pending_edits() {
+        let mut editor = Editor::new(Preset::Default.into());
+        let start = Instant::now();
+
+        editor.edit(edited(0.01), start);
+        editor.set_definition(Preset::Archipelago.into());
+
+        assert_eq!(editor.tick(SIZE, 1.0, after(start, 1000)), None);
+        assert_eq!(editor.draft, Preset::Archipelago.into());
+    }
+
+    #[test]
+    fn visible_panel_is_laid_out() {
+        let mut editor = Editor::new(Preset::Default.into());
+        let start = Instant::now();
+
+        editor.tick(SIZE, 1.0, start);
+        assert!(editor.batch().primitives.is_empty());
+
+        // egui sizes new windows on their first pass without drawing them
+        editor.toggle();
+        editor.tick(SIZE, 1.0, start);
+        editor.tick(SIZE, 1.0, start);
+        assert!(!editor.batch().primitives.is_empty());
+        assert!(!editor.take_textures().set.is_empty());
+
+        editor.cursor_moved([SIZE[0] - 20.0, 260.0]);
+        editor.tick(SIZE, 1.0, start);
+        assert!(editor.wants_mouse());
+    }
+}
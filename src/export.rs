@@ -0,0 +1,122 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use crate::{
+    settings::{ExportArgs, Settings},
+    world::{ChunkKey, ConfigError, HeapTexels, World, WorldConfig, WorldDefinition, WorldError},
+};
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    Config(ConfigError),
+    World(WorldError),
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(_) => write!(f, "unable to write image"),
+            ExportError::Config(_) => write!(f, "unable to generate chunk"),
+            ExportError::World(_) => write!(f, "unable to generate world"),
+        }
+    }
+}
+
+impl Error for ExportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExportError::Io(e) => Some(e),
+            ExportError::Config(e) => Some(e),
+            ExportError::World(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(e: io::Error) -> Self {
+        ExportError::Io(e)
+    }
+}
+
+impl From<ConfigError> for ExportError {
+    fn from(e: ConfigError) -> Self {
+        ExportError::Config(e)
+    }
+}
+
+impl From<WorldError> for ExportError {
+    fn from(e: WorldError) -> Self {
+        ExportError::World(e)
+    }
+}
+
+/// Generates every chunk between the two corners given and writes them out as a single
+/// binary PPM image.
+pub fn export(
+    settings: &Settings,
+    config: WorldConfig,
+    definition: WorldDefinition,
+    args: &ExportArgs,
+) -> Result<(), ExportError> {
+    let (from, to) = (args.from(), args.to());
+    let min = ChunkKey::new(from.x.min(to.x), from.y.min(to.y));
+    let max = ChunkKey::new(from.x.max(to.x), from.y.max(to.y));
+
+    let world = World::new(
+        config,
+        settings.seed,
+        definition,
+        settings.workers,
+        settings.queue_limits,
+        HeapTexels,
+        || (),
+    )?;
+    let keys = (min.y..=max.y).flat_map(|y| (min.x..=max.x).map(move |x| ChunkKey::new(x, y)));
+    let mut textures = HashMap::new();
+
+    world.generate_all(keys, |generated| {
+        textures.insert(generated.chunk.key, generated.texels?.texture);
+        Ok::<_, ExportError>(())
+    })?;
+
+    let chunk_size = config.chunk_size() as usize;
+    let columns = (max.x - min.x + 1) as usize;
+    let rows = (max.y - min.y + 1) as usize;
+
+    let mut output = BufWriter::new(File::create(&args.output)?);
+    write!(
+        output,
+        "P6\n{} {}\n255\n",
+        columns * chunk_size,
+        rows * chunk_size
+    )?;
+
+    for y in min.y..=max.y {
+        for row in 0..chunk_size {
+            for x in min.x..=max.x {
+                let texture = &textures[&ChunkKey::new(x, y)];
+                let start = row * chunk_size * 4;
+
+                for pixel in texture[start..start + chunk_size * 4].chunks_exact(4) {
+                    output.write_all(&pixel[..3])?;
+                }
+            }
+        }
+    }
+
+    output.flush()?;
+
+    println!(
+        "Exported {} chunks to {}",
+        columns * rows,
+        args.output.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{self, IsTerminal},
+    path::PathBuf,
+};
+
+use tracing::Level;
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::{
+    filter::{ParseError, Targets},
+    prelude::*,
+    EnvFilter,
+};
+
+/// Used when neither the command line, the environment nor the config file gives a filter.
+pub const DEFAULT_FILTER: &str = "info";
+
+#[derive(Debug, Clone)]
+pub struct LogSettings {
+    /// A level, such as `debug`, or a list of directives, such as `warn,worldviewer=trace`.
+    pub filter: String,
+    pub trace_file: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub enum LoggingError {
+    InvalidFilter(String, ParseError),
+    UnableToCreateTraceFile(PathBuf, io::Error),
+}
+
+impl Display for LoggingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LoggingError::InvalidFilter(filter, _) => write!(f, "invalid log filter '{}'", filter),
+            LoggingError::UnableToCreateTraceFile(path, _) => {
+                write!(f, "unable to create trace file {}", path.display())
+            }
+        }
+    }
+}
+
+impl Error for LoggingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoggingError::InvalidFilter(_, e) => Some(e),
+            LoggingError::UnableToCreateTraceFile(_, e) => Some(e),
+        }
+    }
+}
+
+/// Logs to stderr, and records a Chrome trace if a file is given. The trace only contains
+/// this crate's spans and events, but all of them regardless of the log filter, and is
+/// finished when the returned guard is dropped.
+pub fn init(settings: &LogSettings) -> Result<Option<FlushGuard>, LoggingError> {
+    let filter = EnvFilter::try_new(&settings.filter)
+        .map_err(|e| LoggingError::InvalidFilter(settings.filter.clone(), e))?;
+
+    let (chrome, guard) = match &settings.trace_file {
+        Some(path) => {
+            let file = File::create(path)
+                .map_err(|e| LoggingError::UnableToCreateTraceFile(path.clone(), e))?;
+
+            let (layer, guard) = ChromeLayerBuilder::new()
+                .writer(file)
+                .include_args(true)
+                .build();
+
+            let targets = Targets::new().with_target(env!("CARGO_CRATE_NAME"), Level::TRACE);
+            (Some(layer.with_filter(targets)), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(io::stderr)
+                .with_ansi(io::stderr().is_terminal())
+                .with_filter(filter),
+        )
+        .with(chrome)
+        .init();
+
+    Ok(guard)
+}
@@ -1,94 +1,507 @@
-use enumset::{EnumSet, EnumSetType};
-use renderer::{InitError, Renderer};
+use app::{Action, App, Data, Event, Handler, Wake};
+use bookmarks::Bookmarks;
+use clap::Parser;
+use coords::ScreenPos;
+use editor::{Editor, EditorAction};
+use reload::DefinitionWatcher;
+use renderer::{
+    InitError, PendingTexture, PrimitiveBatch, RenderError, Renderer, StagingBuffer, StagingPool,
+    TextBatch,
+};
+use settings::{random_seed, Cli, Mode, Settings};
 use stateloop::{
-    app::{App, Data, Event, Window},
-    state::Action,
-    states,
-    winit::event::{ElementState, VirtualKeyCode},
+    app::{EventLoop, Window},
+    error::AppError,
+    winit::event::{ElementState, MouseButton, VirtualKeyCode},
 };
 use std::{
-    collections::{
-        hash_map::Entry::{Occupied, Vacant},
-        HashMap,
-    },
-    sync::Arc,
+    cell::{Cell, RefCell},
+    env,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    mem,
+    path::Path,
+    process,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
-use vulkano::{
-    format::Format,
-    image::ImageViewAbstract,
-    instance::{Instance, InstanceCreateInfo},
-    swapchain::Surface,
+use tracing::warn;
+use viewer::{ChunkState, Command, FrameTimer, InputState, Target, Viewer, ViewerEvent};
+use vulkano::{format::Format, image::ImageViewAbstract, swapchain::Surface};
+use world::{
+    Chunk, ChunkKey, ChunkTexels, ConfigError, Requested, World, WorldConfig, WorldDefinition,
 };
-use world::{ChunkKey, World};
 
+mod app;
+mod bench;
+mod bookmarks;
+mod coords;
+mod editor;
+mod export;
+mod logging;
+mod reload;
 mod renderer;
+mod settings;
+mod ui;
+mod viewer;
 mod world;
 
-#[derive(Debug, EnumSetType)]
-pub enum InputState {
-    Up,
-    Down,
-    Right,
-    Left,
-}
+const HUD_POSITION: [f32; 2] = [10.0, 10.0];
+const HUD_SCALE: f32 = 2.0;
+const PROMPT_HEIGHT: f32 = 40.0;
+const GRID_LINE_WIDTH: f32 = 2.0;
+const GRID_LABEL_OFFSET: f32 = 6.0;
+const GRID_LABEL_SCALE: f32 = 2.0;
+const MINIMAP_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.8];
+const MINIMAP_BORDER: [f32; 4] = [1.0, 1.0, 1.0, 0.8];
+const MINIMAP_VIEW: [f32; 4] = [1.0, 0.9, 0.2, 1.0];
+const BOOKMARKS_PATH: &str = "bookmarks.txt";
+/// Where the editor saves to when no definition file was given.
+const DEFAULT_DEFINITION_PATH: &str = "world.toml";
 
-states! {
-    State {
-        MainHandler Main(input: EnumSet<InputState>)
-    }
+struct Storage {
+    config: WorldConfig,
+    tick_rate: u32,
+    renderer: Renderer,
+    world: World<Arc<StagingPool>>,
+    viewer: Viewer<Texture>,
+    bookmarks: Option<Bookmarks>,
+    /// Set when the world comes from a definition file.
+    watcher: Option<DefinitionWatcher>,
+    editor: Editor,
+    frame_timer: RefCell<FrameTimer>,
+    /// Set when drawing fails, to be dealt with on the next tick.
+    render_error: RefCell<Option<RenderError>>,
+    upload_budget: usize,
+    /// Chunks started uploading since the last frame.
+    uploads_started: Cell<usize>,
+    /// Shown once both of their textures have finished uploading.
+    uploads: Vec<PendingChunk>,
+    /// Chunks whose textures were lost with the renderer, to be uploaded again.
+    backlog: Vec<Chunk>,
 }
 
-enum TextureEntry {
-    Requested,
-    Valid(Arc<dyn ImageViewAbstract>),
+struct PendingChunk {
+    chunk: Chunk,
+    /// The world's epoch when the chunk was collected, so it is not shown if the world has
+    /// since changed.
+    epoch: u64,
+    texture: PendingTexture,
+    thumbnail: PendingTexture,
 }
 
-struct Storage {
-    renderer: Renderer,
-    world: World,
-    bounds: (u32, u32),
-    offset: (i64, i64),
-    chunk_offset: (i64, i64),
-    textures: HashMap<ChunkKey, TextureEntry>,
+/// Why a generated chunk could not be turned into textures.
+#[derive(Debug)]
+enum UploadError {
+    InvalidChunk(ConfigError),
+    Render(RenderError),
 }
 
 type AppData = Data<Storage, Arc<Surface<Window>>>;
+type Texture = Arc<dyn ImageViewAbstract>;
+
+impl Display for UploadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadError::InvalidChunk(_) => write!(f, "invalid chunk"),
+            UploadError::Render(_) => write!(f, "unable to upload texture"),
+        }
+    }
+}
+
+impl Error for UploadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            UploadError::InvalidChunk(e) => Some(e),
+            UploadError::Render(e) => Some(e),
+        }
+    }
+}
 
 impl Storage {
-    fn new(surface: &Arc<Surface<Window>>, renderer: Renderer) -> Self {
-        let mut storage = Self {
+    fn new(
+        surface: &Arc<Surface<Window>>,
+        renderer: Renderer,
+        world: World<Arc<StagingPool>>,
+        watcher: Option<DefinitionWatcher>,
+        config: WorldConfig,
+        settings: &Settings,
+    ) -> Self {
+        let (width, height) = Self::window_bounds(surface);
+
+        let bookmarks = load_bookmarks(settings.seed);
+        let editor = Editor::new(world.definition());
+
+        let mut viewer = Viewer::new(config, width, height);
+        viewer.go_to(Target::Tile(settings.start));
+
+        Self {
+            config,
+            tick_rate: settings.tick_rate,
             renderer,
-            world: World::new(),
-            bounds: (0, 0),
-            offset: (0, 0),
-            chunk_offset: (0, 0),
-            textures: HashMap::new(),
+            world,
+            viewer,
+            bookmarks,
+            watcher,
+            editor,
+            frame_timer: RefCell::new(FrameTimer::new()),
+            render_error: RefCell::new(None),
+            upload_budget: settings.upload_budget,
+            uploads_started: Cell::new(0),
+            uploads: Vec::new(),
+            backlog: Vec::new(),
+        }
+    }
+
+    /// Rebuilds the renderer after the device has been lost, then uploads the resident
+    /// chunks again. Any other rendering error ends the session.
+    fn recover(
+        &mut self,
+        surface: &Arc<Surface<Window>>,
+        error: RenderError,
+    ) -> Result<(), Box<dyn Error>> {
+        if !error.is_recoverable() {
+            return Err(error.into());
+        }
+
+        warn!("Rebuilding renderer, {}", error);
+
+        let device_lost = matches!(error, RenderError::DeviceLost);
+        let epoch = self.world.epoch();
+
+        for upload in self.uploads.drain(..) {
+            let current = upload.epoch == epoch;
+            let chunk = upload.cancel(device_lost);
+
+            if current {
+                self.backlog.push(chunk);
+            }
+        }
+
+        self.renderer.recover(surface)?;
+        self.backlog.extend(self.viewer.take_resident());
+        Ok(())
+    }
+
+    /// Starts uploading generated chunks, and any to be uploaded again, up to the budget
+    /// for this frame.
+    fn start_uploads(&mut self) {
+        let pool = self.renderer.staging_pool();
+
+        while self.uploads_started.get() < self.upload_budget {
+            let (chunk, texels) = match self.backlog.pop() {
+                Some(chunk) => {
+                    let palette = self.world.definition().terrain.palette;
+                    let texels = chunk.texels(&self.config, &palette, &pool);
+                    (chunk, texels)
+                }
+                None => match self.world.get_chunk_result() {
+                    Some(generated) => (generated.chunk, generated.texels),
+                    None => break,
+                },
+            };
+
+            self.uploads_started.set(self.uploads_started.get() + 1);
+
+            match upload_chunk(&self.renderer, &self.config, chunk.key, texels) {
+                Ok((texture, thumbnail)) => self.uploads.push(PendingChunk {
+                    chunk,
+                    epoch: self.world.epoch(),
+                    texture,
+                    thumbnail,
+                }),
+                Err(UploadError::Render(e)) if e.is_recoverable() => {
+                    // Uploaded again once the renderer has been rebuilt
+                    self.backlog.push(chunk);
+                    self.render_error.replace(Some(e));
+                    break;
+                }
+                Err(e) => self.discard(chunk.key, &e),
+            }
+        }
+    }
+
+    /// Shows the chunks whose uploads have finished.
+    fn finish_uploads(&mut self) {
+        for mut upload in mem::take(&mut self.uploads) {
+            let ready = upload
+                .texture
+                .poll()
+                .and_then(|texture| Ok(texture.zip(upload.thumbnail.poll()?)));
+
+            match ready {
+                Ok(Some(_)) if upload.epoch != self.world.epoch() => (),
+                Ok(Some((texture, thumbnail))) => {
+                    self.viewer.insert_chunk(upload.chunk, texture, thumbnail)
+                }
+                Ok(None) => self.uploads.push(upload),
+                Err(e) if e.is_recoverable() => {
+                    self.uploads.push(upload);
+                    self.render_error.replace(Some(e));
+                }
+                Err(e) => {
+                    let key = upload.chunk.key;
+                    upload.cancel(false);
+                    self.discard(key, &UploadError::Render(e));
+                }
+            }
+        }
+    }
+
+    /// Switches the world to `seed` and `definition`, dropping every chunk of the old one.
+    /// The renderer and camera are left as they are, and the visible chunks are requested
+    /// again on the next tick.
+    ///
+    /// A new seed or preset gives a different world, so nothing of the old one is drawn.
+    /// Adjusting the terrain, such as from the editor, leaves the old chunks drawn until
+    /// they are replaced.
+    fn reconfigure(&mut self, seed: u64, definition: WorldDefinition) {
+        let switched =
+            seed != self.world.seed() || definition.preset != self.world.definition().preset;
+
+        if seed != self.world.seed() {
+            self.bookmarks = load_bookmarks(seed);
+        }
+
+        if switched {
+            self.viewer.clear_chunks();
+        } else {
+            self.viewer.outdate_chunks();
+        }
+
+        self.world.reconfigure(seed, definition);
+        self.editor.set_definition(definition);
+        self.backlog.clear();
+
+        // Uploads already submitted are left to finish, then thrown away
+        self.viewer
+            .set_status(format!("Seed {}, preset {}", seed, definition));
+    }
+
+    /// Regenerates with the definition file if it has changed. If it no longer reads, the
+    /// error is shown and the current world kept.
+    fn reload_definition(&mut self) {
+        let Some(watcher) = &self.watcher else {
+            return;
         };
 
-        storage.update_bounds(surface);
-        storage
+        let status = match watcher.poll() {
+            // Such as after saving from the editor
+            Some(Ok(definition)) if definition == self.world.definition() => return,
+            Some(Ok(definition)) => {
+                let status = format!("Reloaded {}", watcher.path().display());
+                self.reconfigure(self.world.seed(), definition);
+                status
+            }
+            Some(Err(e)) => {
+                warn!("Keeping the current world: {}", describe(&e));
+                format!("Error: {}", describe(&e))
+            }
+            None => return,
+        };
+
+        self.viewer.set_status(status);
+    }
+
+    fn run_editor(&mut self, action: EditorAction) {
+        match action {
+            EditorAction::Apply(definition) => self.reconfigure(self.world.seed(), definition),
+            EditorAction::Save(definition) => {
+                if definition != self.world.definition() {
+                    self.reconfigure(self.world.seed(), definition);
+                }
+
+                let path = self
+                    .watcher
+                    .as_ref()
+                    .map_or(Path::new(DEFAULT_DEFINITION_PATH), DefinitionWatcher::path);
+
+                let status = match self.world.definition().save(path) {
+                    Ok(()) => format!("Saved {}", path.display()),
+                    Err(e) => {
+                        warn!("Unable to save world definition: {}", describe(&e));
+                        format!("Error: {}", describe(&e))
+                    }
+                };
+
+                self.viewer.set_status(status);
+            }
+        }
+    }
+
+    fn discard(&mut self, key: ChunkKey, error: &UploadError) {
+        warn!(
+            x = key.x,
+            y = key.y,
+            "Discarding chunk: {}",
+            describe(error)
+        );
+        self.viewer.fail_chunk(key);
+    }
+
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::GoTo(target) => self.viewer.go_to(target),
+            Command::Bookmark(name) => {
+                let position = self.viewer.position();
+                let status = match &mut self.bookmarks {
+                    Some(bookmarks) => match bookmarks.add(name.clone(), position) {
+                        Ok(()) => format!("Saved bookmark '{}'", name),
+                        Err(e) => format!("Unable to save bookmark: {}", e),
+                    },
+                    None => "Bookmarks are disabled".into(),
+                };
+
+                self.viewer.set_status(status);
+            }
+            Command::Seed(seed) => self.reconfigure(seed, self.world.definition()),
+            Command::Preset(preset) => self.reconfigure(self.world.seed(), preset.into()),
+        }
     }
 
-    fn update_bounds(&mut self, surface: &Arc<Surface<Window>>) {
+    fn next_bookmark(&mut self) {
+        let bookmark = self.bookmarks.as_mut().and_then(Bookmarks::next);
+
+        match bookmark {
+            Some(bookmark) => {
+                let status = format!("Bookmark '{}'", bookmark.name);
+                self.viewer.go_to(Target::Tile(bookmark.position));
+                self.viewer.set_status(status);
+            }
+            None => self.viewer.set_status("No bookmarks for this seed"),
+        }
+    }
+
+    fn window_bounds(surface: &Arc<Surface<Window>>) -> (u32, u32) {
         let window_bounds = surface
             .window()
             .inner_size()
             .to_logical::<u32>(surface.window().scale_factor());
-        let chunk_size = 300u32;
-        self.bounds = (
-            window_bounds.width / chunk_size,
-            window_bounds.height / chunk_size,
-        );
+
+        (window_bounds.width, window_bounds.height)
     }
 }
 
-impl MainHandler for AppData {
-    fn handle_event(
-        &mut self,
-        event: Event,
-        mut input_state: EnumSet<InputState>,
-    ) -> Action<State> {
+impl Handler for AppData {
+    type Error = Box<dyn Error>;
+
+    fn handle_event(&mut self, event: Event) -> Action {
         match event {
+            // Releases carry on below, so keys held before typing began are let go of
+            Event::KeyboardInput { ref input, .. }
+                if self.data.editor.wants_keyboard() && input.state == ElementState::Pressed =>
+            {
+                if let Some(code) = input.virtual_keycode {
+                    self.data.editor.key(code);
+                }
+
+                Action::Continue
+            }
+            Event::ReceivedCharacter(c) if self.data.editor.wants_keyboard() => {
+                self.data.editor.text(c);
+                Action::Continue
+            }
+            Event::KeyboardInput { ref input, .. } if self.data.viewer.prompt_open() => {
+                if input.state == ElementState::Pressed {
+                    match input.virtual_keycode {
+                        Some(VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter) => {
+                            match self.data.viewer.submit_prompt() {
+                                Some(Ok(command)) => self.data.run_command(command),
+                                Some(Err(e)) => {
+                                    self.data.viewer.set_status(format!("Error: {}", e))
+                                }
+                                None => (),
+                            }
+                        }
+                        Some(VirtualKeyCode::Back) => {
+                            self.data.viewer.handle_event(ViewerEvent::PromptBackspace)
+                        }
+                        Some(VirtualKeyCode::Escape) => {
+                            self.data.viewer.handle_event(ViewerEvent::CancelPrompt)
+                        }
+                        _ => (),
+                    }
+                }
+
+                Action::Continue
+            }
+            Event::ReceivedCharacter(c) if self.data.viewer.prompt_open() => {
+                if !c.is_control() {
+                    self.data.viewer.handle_event(ViewerEvent::PromptInput(c));
+                }
+
+                Action::Continue
+            }
+            Event::KeyboardInput { ref input, .. }
+                if matches!(
+                    input.virtual_keycode,
+                    Some(VirtualKeyCode::Return | VirtualKeyCode::Tab)
+                ) =>
+            {
+                if input.state == ElementState::Pressed {
+                    match input.virtual_keycode {
+                        Some(VirtualKeyCode::Return) => {
+                            self.data.viewer.handle_event(ViewerEvent::OpenPrompt)
+                        }
+                        _ => self.data.next_bookmark(),
+                    }
+                }
+
+                Action::Continue
+            }
+            Event::KeyboardInput { ref input, .. }
+                if matches!(
+                    input.virtual_keycode,
+                    Some(
+                        VirtualKeyCode::R
+                            | VirtualKeyCode::LBracket
+                            | VirtualKeyCode::RBracket
+                            | VirtualKeyCode::Comma
+                            | VirtualKeyCode::Period
+                    )
+                ) =>
+            {
+                if input.state == ElementState::Pressed {
+                    let (seed, definition) = (self.data.world.seed(), self.data.world.definition());
+                    let preset = definition.preset;
+
+                    match input.virtual_keycode {
+                        Some(VirtualKeyCode::R) => self.data.reconfigure(random_seed(), definition),
+                        Some(VirtualKeyCode::LBracket) => {
+                            self.data.reconfigure(seed.wrapping_sub(1), definition)
+                        }
+                        Some(VirtualKeyCode::RBracket) => {
+                            self.data.reconfigure(seed.wrapping_add(1), definition)
+                        }
+                        Some(VirtualKeyCode::Comma) => {
+                            self.data.reconfigure(seed, preset.previous().into())
+                        }
+                        _ => self.data.reconfigure(seed, preset.next().into()),
+                    }
+                }
+
+                Action::Continue
+            }
+            Event::KeyboardInput { ref input, .. }
+                if matches!(
+                    input.virtual_keycode,
+                    Some(VirtualKeyCode::F1 | VirtualKeyCode::F2 | VirtualKeyCode::F3)
+                ) =>
+            {
+                if input.state == ElementState::Pressed {
+                    match input.virtual_keycode {
+                        Some(VirtualKeyCode::F1) => {
+                            self.data.viewer.handle_event(ViewerEvent::ToggleHud)
+                        }
+                        Some(VirtualKeyCode::F2) => {
+                            self.data.viewer.handle_event(ViewerEvent::ToggleGrid)
+                        }
+                        _ => self.data.editor.toggle(),
+                    }
+                }
+
+                Action::Continue
+            }
             Event::KeyboardInput { ref input, .. } => {
                 let input_kind = match input.virtual_keycode {
                     Some(VirtualKeyCode::Up | VirtualKeyCode::W) => InputState::Up,
@@ -98,17 +511,64 @@ impl MainHandler for AppData {
                     _ => return Action::Continue,
                 };
 
-                if input.state == ElementState::Pressed {
-                    input_state.insert(input_kind);
-                } else {
-                    input_state.remove(input_kind);
+                self.data.viewer.handle_event(match input.state {
+                    ElementState::Pressed => ViewerEvent::Pressed(input_kind),
+                    ElementState::Released => ViewerEvent::Released(input_kind),
+                });
+
+                Action::Continue
+            }
+            Event::Resized(size) => {
+                self.data.renderer.resized();
+
+                // Minimising shrinks the window to nothing, which would evict every chunk
+                if size.width > 0 && size.height > 0 {
+                    let (width, height) = Storage::window_bounds(self.window());
+                    self.data
+                        .viewer
+                        .handle_event(ViewerEvent::Resized { width, height });
+                }
+
+                Action::Continue
+            }
+
+            Event::CursorMoved { position, .. } => {
+                let position = position.to_logical::<f64>(self.window().window().scale_factor());
+                self.data
+                    .editor
+                    .cursor_moved([position.x as f32, position.y as f32]);
+
+                // No tooltip under the editor
+                self.data
+                    .viewer
+                    .handle_event(if self.data.editor.wants_mouse() {
+                        ViewerEvent::CursorLeft
+                    } else {
+                        ViewerEvent::CursorMoved(ScreenPos::new(position.x, position.y))
+                    });
+                Action::Continue
+            }
+            Event::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let pressed = state == ElementState::Pressed;
+
+                if pressed && !self.data.editor.wants_mouse() {
+                    self.data.viewer.handle_event(ViewerEvent::Clicked);
                 }
 
-                Action::Done(State::Main(input_state))
+                self.data.editor.mouse_button(pressed);
+                Action::Continue
+            }
+            Event::MouseWheel { delta, .. } => {
+                self.data.editor.scroll(delta);
+                Action::Continue
             }
-            Event::Resized(..) => {
-                let window = self.window().clone();
-                self.data.update_bounds(&window);
+            Event::CursorLeft { .. } => {
+                self.data.editor.cursor_left();
+                self.data.viewer.handle_event(ViewerEvent::CursorLeft);
                 Action::Continue
             }
 
@@ -117,107 +577,417 @@ impl MainHandler for AppData {
         }
     }
 
-    fn handle_tick(&mut self, input_state: EnumSet<InputState>) {
-        let speed = 5;
+    fn handle_tick(&mut self) -> Result<(), Self::Error> {
+        if let Some(error) = self.data.render_error.take() {
+            let surface = self.window().clone();
+            self.data.recover(&surface, error)?;
+        }
 
-        if input_state.contains(InputState::Up) {
-            self.data.offset.1 += speed;
+        self.data.reload_definition();
+
+        let (width, height) = Storage::window_bounds(self.window());
+        let scale = self.window().window().scale_factor() as f32;
+
+        if let Some(action) =
+            self.data
+                .editor
+                .tick([width as f32, height as f32], scale, Instant::now())
+        {
+            self.data.run_editor(action);
         }
 
-        if input_state.contains(InputState::Down) {
-            self.data.offset.1 -= speed;
+        self.data
+            .renderer
+            .update_gui_textures(self.data.editor.take_textures());
+
+        self.data.finish_uploads();
+        self.data.start_uploads();
+
+        let requests = self
+            .data
+            .viewer
+            .tick(Duration::from_secs(1) / self.data.tick_rate);
+
+        for key in requests.stale {
+            self.data.world.cancel_chunk(key);
         }
 
-        if input_state.contains(InputState::Right) {
-            self.data.offset.0 -= speed;
+        if let Some(focus) = self.data.viewer.focus() {
+            self.data.world.set_focus(focus);
         }
 
-        if input_state.contains(InputState::Left) {
-            self.data.offset.0 += speed;
+        for key in requests.wanted {
+            match self.data.world.request_chunk(key) {
+                Requested::Queued | Requested::Coalesced => (),
+                Requested::Displaced(dropped) => self.data.viewer.drop_request(dropped),
+                Requested::Rejected => self.data.viewer.drop_request(key),
+            }
         }
 
-        self.data.chunk_offset = (
-            (self.data.offset.0 + 150) / 300 as i64,
-            (self.data.offset.1 + 150) / 300 as i64,
-        );
+        Ok(())
+    }
+
+    fn is_busy(&self) -> bool {
+        let data = &self.data;
+        data.render_error.borrow().is_some()
+            || data.viewer.is_panning()
+            || data.world.has_results()
+            || !data.uploads.is_empty()
+            || !data.backlog.is_empty()
+            || data.editor.is_busy()
+    }
+
+    fn handle_render(&self) {
+        self.data.uploads_started.set(0);
 
-        while let Some(chunk) = self.data.world.get_chunk_result() {
-            self.data.textures.insert(
-                chunk.key,
-                TextureEntry::Valid(self.data.renderer.create_texture(
-                    chunk.texture(),
-                    512,
-                    512,
-                    Format::R8G8B8A8_SRGB,
-                )),
+        let mut frame_timer = self.data.frame_timer.borrow_mut();
+        frame_timer.frame(Instant::now());
+
+        let mut grid = PrimitiveBatch::new();
+        let mut labels = TextBatch::new();
+        let mut hud = TextBatch::new();
+
+        for outline in self.data.viewer.grid() {
+            let position = [outline.position.x as f32, outline.position.y as f32];
+            let size = [outline.size as f32; 2];
+            let colour = grid_colour(outline.state);
+
+            grid.rect(position, size, [colour[0], colour[1], colour[2], 0.15]);
+            grid.outline(position, size, GRID_LINE_WIDTH, colour);
+            labels.text(
+                [
+                    position[0] + GRID_LABEL_OFFSET,
+                    position[1] + GRID_LABEL_OFFSET,
+                ],
+                GRID_LABEL_SCALE,
+                colour,
+                &format!("{}, {}", outline.key.x, outline.key.y),
             );
         }
 
-        for x in (0..=self.data.bounds.0 + 2).map(|x| x as i64 - 1) {
-            for y in (0..=self.data.bounds.1 + 2).map(|y| y as i64 - 1) {
-                let key = ChunkKey::new(
-                    x as i64 - self.data.chunk_offset.0,
-                    y as i64 - self.data.chunk_offset.1,
+        if let Some(mut lines) = self.data.viewer.hud(&frame_timer) {
+            lines.push(format!("Seed: {}", self.data.world.seed()));
+            lines.push(format!("Preset: {}", self.data.world.definition()));
+
+            let stats = self.data.world.stats();
+            let limits = self.data.world.limits();
+            lines.push(format!(
+                "Generation: {:.1} ms p50, {:.1} ms p90",
+                stats.generation.p50.as_secs_f64() * 1000.0,
+                stats.generation.p90.as_secs_f64() * 1000.0
+            ));
+            lines.push(format!(
+                "Generated: {} chunks, {:.1} chunks/s",
+                stats.generated, stats.throughput
+            ));
+            lines.push(format!(
+                "Queue: {}/{} waiting, {}/{} ready, {:.1} ms p50 wait",
+                stats.queued,
+                limits.requests,
+                stats.ready,
+                limits.results,
+                stats.queue_wait.p50.as_secs_f64() * 1000.0
+            ));
+            lines.push(format!(
+                "Requests: {} coalesced, {} dropped, {} rejected",
+                stats.requests.coalesced, stats.requests.dropped, stats.requests.rejected
+            ));
+            hud.panel(HUD_POSITION, HUD_SCALE, &lines);
+        }
+
+        if let Some(line) = self.data.viewer.prompt_line() {
+            // Errors can run over several lines, which push the panel up from the bottom
+            let (_, height) = Storage::window_bounds(self.window());
+            let lines = line
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .collect::<Vec<_>>();
+            let extra = lines.len().saturating_sub(1) as f32 * TextBatch::line_height(HUD_SCALE);
+
+            hud.panel(
+                [HUD_POSITION[0], height as f32 - PROMPT_HEIGHT - extra],
+                HUD_SCALE,
+                &lines,
+            );
+        }
+
+        if let Some((position, lines)) = self
+            .data
+            .viewer
+            .tooltip(&self.data.world.definition().terrain.palette, |tile| {
+                self.data.world.sample(tile)
+            })
+        {
+            hud.panel([position.x as f32, position.y as f32], HUD_SCALE, &lines);
+        }
+
+        let minimap = self.data.viewer.minimap();
+        let minimap_size = [minimap.size as f32; 2];
+        let mut minimap_background = PrimitiveBatch::new();
+        let mut minimap_outline = PrimitiveBatch::new();
+
+        minimap_background.rect([0.0, 0.0], minimap_size, MINIMAP_BACKGROUND);
+        minimap_outline.outline(
+            [
+                minimap.view_position.x as f32,
+                minimap.view_position.y as f32,
+            ],
+            [minimap.view_size.0 as f32, minimap.view_size.1 as f32],
+            GRID_LINE_WIDTH,
+            MINIMAP_VIEW,
+        );
+        minimap_outline.outline([0.0, 0.0], minimap_size, GRID_LINE_WIDTH, MINIMAP_BORDER);
+
+        let chunk_display_size = self.data.config.display_size() as f32;
+
+        let result = self.data.renderer.render(self.window(), |mut frame| {
+            for chunk in self.data.viewer.draw_list() {
+                frame = frame.draw(
+                    [chunk.position.x as f32, chunk.position.y as f32].into(),
+                    chunk_display_size,
+                    chunk.texture.clone(),
                 );
+            }
 
-                match self.data.textures.entry(key) {
-                    Occupied(_) => continue,
-                    Vacant(entry) => {
-                        entry.insert(TextureEntry::Requested);
-                        self.data.world.request_chunk(key);
-                    }
-                }
+            let mut inset = frame.primitives(&grid).text(&labels).inset(
+                [minimap.position.x as f32, minimap.position.y as f32],
+                minimap_size,
+            );
+
+            inset = inset.primitives(&minimap_background);
+
+            for chunk in &minimap.chunks {
+                inset = inset.draw(
+                    [chunk.position.x as f32, chunk.position.y as f32].into(),
+                    minimap.chunk_size as f32,
+                    chunk.texture.clone(),
+                );
             }
+
+            inset
+                .primitives(&minimap_outline)
+                .end_inset()
+                .text(&hud)
+                .gui(self.data.editor.batch())
+                .finish()
+        });
+
+        if let Err(e) = result {
+            self.data.render_error.replace(Some(e));
         }
     }
+}
 
-    fn handle_render(&self, _: EnumSet<InputState>) {
-        self.data.renderer.render(self.window(), |mut frame| {
-            for x in (0..=self.data.bounds.0 + 2).map(|x| x as i64 - 1) {
-                for y in (0..=self.data.bounds.1 + 2).map(|y| y as i64 - 1) {
-                    let key = ChunkKey::new(
-                        x as i64 - self.data.chunk_offset.0,
-                        y as i64 - self.data.chunk_offset.1,
-                    );
-                    if let Some(&TextureEntry::Valid(ref texture)) = self.data.textures.get(&key) {
-                        frame = frame.draw(
-                            [
-                                (self.data.offset.0 + key.x * 300) as f32,
-                                (self.data.offset.1 + key.y * 300) as f32,
-                            ]
-                            .into(),
-                            texture.clone(),
-                        );
-                    }
-                }
-            }
+/// Starts uploading the full texture and minimap thumbnail for a chunk from its texels.
+fn upload_chunk(
+    renderer: &Renderer,
+    config: &WorldConfig,
+    key: ChunkKey,
+    texels: Result<ChunkTexels<StagingBuffer>, ConfigError>,
+) -> Result<(PendingTexture, PendingTexture), UploadError> {
+    let chunk_size = config.chunk_size();
+    let thumbnail_size = config.thumbnail_size();
+    let texels = texels.map_err(UploadError::InvalidChunk)?;
 
-            frame.finish()
-        });
+    let texture = renderer
+        .create_texture(
+            &format!("chunk ({}, {})", key.x, key.y),
+            texels.texture,
+            chunk_size,
+            chunk_size,
+            Format::R8G8B8A8_SRGB,
+        )
+        .map_err(UploadError::Render)?;
+
+    match renderer.create_texture(
+        &format!("chunk ({}, {}) thumbnail", key.x, key.y),
+        texels.thumbnail,
+        thumbnail_size,
+        thumbnail_size,
+        Format::R8G8B8A8_SRGB,
+    ) {
+        Ok(thumbnail) => Ok((texture, thumbnail)),
+        Err(e) => {
+            // The device may have been lost, so the texture's upload cannot be waited on
+            texture.abandon();
+            Err(UploadError::Render(e))
+        }
+    }
+}
+
+/// A bookmark file that cannot be read is left alone rather than overwritten.
+fn load_bookmarks(seed: u64) -> Option<Bookmarks> {
+    match Bookmarks::load(BOOKMARKS_PATH, seed) {
+        Ok(bookmarks) => Some(bookmarks),
+        Err(e) => {
+            warn!(
+                path = BOOKMARKS_PATH,
+                "Bookmarks disabled, unable to load: {}", e
+            );
+            None
+        }
+    }
+}
+
+impl PendingChunk {
+    /// Stops waiting for the uploads, without waiting on a device that has been lost.
+    fn cancel(self, device_lost: bool) -> Chunk {
+        if device_lost {
+            self.texture.abandon();
+            self.thumbnail.abandon();
+        }
+
+        self.chunk
+    }
+}
+
+fn grid_colour(state: Option<ChunkState>) -> [f32; 4] {
+    match state {
+        None => [1.0, 1.0, 1.0, 1.0],
+        Some(ChunkState::Requested) => [1.0, 0.8, 0.0, 1.0],
+        Some(ChunkState::Valid) => [0.0, 1.0, 0.3, 1.0],
+        Some(ChunkState::Failed) => [1.0, 0.1, 0.1, 1.0],
+        Some(ChunkState::Evicted) => [0.6, 0.4, 1.0, 1.0],
+        Some(ChunkState::Outdated) => [0.3, 0.6, 1.0, 1.0],
     }
 }
 
 fn main() {
-    let instance = {
-        let required_extensions = vulkano_win::required_extensions();
-
-        Instance::new(InstanceCreateInfo {
-            enabled_extensions: required_extensions,
-            enumerate_portability: true,
-            ..Default::default()
-        })
-        .expect("Unable to initialise vulkan")
+    let cli = Cli::parse();
+
+    let settings = match cli.options.settings(env::var("RUST_LOG").ok()) {
+        Ok(settings) => settings,
+        Err(e) => {
+            report(&e);
+            process::exit(2);
+        }
     };
 
+    let trace = match logging::init(&settings.log) {
+        Ok(trace) => trace,
+        Err(e) => {
+            report(&e);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(cli, settings) {
+        // Exiting skips destructors, so the trace has to be finished here
+        drop(trace);
+        report(e.as_ref());
+        process::exit(1);
+    }
+}
+
+fn run(cli: Cli, settings: Settings) -> Result<(), Box<dyn Error>> {
+    if cli.list_devices {
+        return Ok(list_devices(&settings)?);
+    }
+
+    let config = WorldConfig::new(settings.chunk_size, settings.display_size)?;
+    let definition = match &settings.definition {
+        Some(path) => WorldDefinition::load(path, settings.preset)?,
+        None => settings.preset.into(),
+    };
+
+    match cli.mode.unwrap_or(Mode::View) {
+        Mode::View => view(settings, config, definition),
+        Mode::Export(args) => Ok(export::export(&settings, config, definition, &args)?),
+        Mode::Bench(args) => Ok(bench::bench(&settings, config, definition, &args)?),
+    }
+}
+
+/// Prints an error for the user, followed by what caused it.
+fn report(error: &dyn Error) {
+    eprintln!("worldviewer: {}", describe(error));
+}
+
+/// An error's message with those of its sources appended, most specific last.
+fn describe(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+
+    let mut previous = message.clone();
+
+    while let Some(error) = source {
+        // Some errors repeat their source's message as their own
+        let current = error.to_string();
+
+        if current != previous {
+            message.push_str(&format!(": {}", current));
+        }
+
+        previous = current;
+        source = error.source();
+    }
+
+    message
+}
+
+fn list_devices(settings: &Settings) -> Result<(), InitError> {
+    let instance = Renderer::create_instance(settings.renderer.debug)?;
+    let event_loop = EventLoop::new();
+    let surface = Renderer::construct_probe_window(&event_loop, instance.clone())?;
+
+    for device in Renderer::describe_devices(&instance, &surface) {
+        println!("{}", device);
+    }
+
+    Ok(())
+}
+
+fn view(
+    settings: Settings,
+    config: WorldConfig,
+    definition: WorldDefinition,
+) -> Result<(), Box<dyn Error>> {
+    let instance = Renderer::create_instance(settings.renderer.debug)?;
     let constructor_instance = instance.clone();
+    let tick_rate = settings.tick_rate;
+    let redraw = settings.redraw;
 
-    App::new(
-        move |event_loop| Renderer::construct_window(event_loop, constructor_instance),
-        move |surface| -> Result<_, InitError> {
-            let renderer = Renderer::init_vulkan(&instance, surface)?;
-            Ok(Storage::new(surface, renderer))
+    let mut app = App::new(
+        move |event_loop| {
+            Renderer::construct_window(event_loop, constructor_instance, settings.window_size)
+        },
+        move |surface, proxy| -> Result<_, Box<dyn Error>> {
+            let renderer = Renderer::init_vulkan(&instance, surface, &config, &settings.renderer)?;
+
+            let watcher = match &settings.definition {
+                Some(path) => {
+                    let proxy = proxy.clone();
+                    Some(DefinitionWatcher::new(path, settings.preset, move || {
+                        let _ = proxy.send_event(Wake);
+                    })?)
+                }
+                None => None,
+            };
+
+            let proxy = Mutex::new(proxy);
+
+            // Generation writes textures straight into the renderer's staging buffers
+            let world = World::new(
+                config,
+                settings.seed,
+                definition,
+                settings.workers,
+                settings.queue_limits,
+                renderer.staging_pool(),
+                move || {
+                    // Only fails once the event loop has gone, when nobody is waiting
+                    let _ = proxy.lock().unwrap().send_event(Wake);
+                },
+            )?;
+
+            Ok(Storage::new(
+                surface, renderer, world, watcher, config, &settings,
+            ))
         },
     )
-    .expect("Unable to initialise application")
-    .run(60, State::Main(EnumSet::empty()))
+    .map_err(|e| match e {
+        AppError::WindowError(e) => e.into(),
+        AppError::DataError(e) => e,
+    })?;
+
+    app.run(tick_rate, redraw)
 }
@@ -0,0 +1,90 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use crossbeam_channel::{Receiver, Sender};
+use tracing::debug;
+
+use crate::world::{DefinitionError, Preset, WorldDefinition};
+
+/// How often the file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches a world definition file from a thread of its own, reading it again whenever
+/// its modification time changes.
+pub struct DefinitionWatcher {
+    path: PathBuf,
+    rx: Receiver<Result<WorldDefinition, DefinitionError>>,
+    _thread: JoinHandle<()>,
+}
+
+impl DefinitionWatcher {
+    /// Starts watching `path`, which has already been read, taking anything it does not
+    /// give from `preset`. `notify` is called from the watching thread after each reload.
+    pub fn new<P, N>(path: P, preset: Preset, notify: N) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+        N: Fn() + Send + 'static,
+    {
+        let path = path.as_ref().to_path_buf();
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let thread_path = path.clone();
+
+        let thread = thread::Builder::new()
+            .name("World Viewer Definition Watcher".into())
+            .spawn(move || watch(&thread_path, preset, &tx, notify))?;
+
+        Ok(Self {
+            path,
+            rx,
+            _thread: thread,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The outcome of the latest reload since the last call, if there has been one.
+    pub fn poll(&self) -> Option<Result<WorldDefinition, DefinitionError>> {
+        self.rx.try_iter().last()
+    }
+}
+
+fn watch<N: Fn()>(
+    path: &Path,
+    preset: Preset,
+    tx: &Sender<Result<WorldDefinition, DefinitionError>>,
+    notify: N,
+) {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    let mut last: Option<SystemTime> = modified(path).ok();
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        let current = match modified(path) {
+            Ok(time) => Some(time),
+            // Reported once, then again when the file comes back
+            Err(_) if last.is_none() => continue,
+            Err(_) => None,
+        };
+
+        if current == last {
+            continue;
+        }
+
+        last = current;
+        debug!(path = %path.display(), "Reloading world definition");
+
+        // Stops once the viewer has gone
+        if tx.send(WorldDefinition::load(path, preset)).is_err() {
+            return;
+        }
+
+        notify();
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use tracing::{debug, error, trace, warn};
+use vulkano::{
+    device::DeviceOwned,
+    instance::{
+        debug::{
+            DebugUtilsMessageSeverity, DebugUtilsMessageType, DebugUtilsMessenger,
+            DebugUtilsMessengerCreateInfo, Message,
+        },
+        layers_list, Instance, InstanceExtensions,
+    },
+    VulkanObject,
+};
+
+const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+pub fn layers() -> Vec<String> {
+    let installed =
+        layers_list().map(|mut layers| layers.any(|layer| layer.name() == VALIDATION_LAYER));
+
+    match installed {
+        Ok(true) => vec![VALIDATION_LAYER.into()],
+        Ok(false) => {
+            warn!(
+                "{} is not installed, continuing without it",
+                VALIDATION_LAYER
+            );
+            Vec::new()
+        }
+        Err(e) => {
+            warn!("Unable to list Vulkan layers: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+pub fn extensions() -> InstanceExtensions {
+    let supported =
+        InstanceExtensions::supported_by_core().is_ok_and(|extensions| extensions.ext_debug_utils);
+
+    if !supported {
+        warn!("VK_EXT_debug_utils is not supported, Vulkan messages will not be logged");
+    }
+
+    InstanceExtensions {
+        ext_debug_utils: supported,
+        ..InstanceExtensions::none()
+    }
+}
+
+/// Forwards every message from the layers and driver to the log. Passed to instance
+/// creation as well, which happens before any messenger can exist.
+pub fn messenger_info() -> DebugUtilsMessengerCreateInfo {
+    DebugUtilsMessengerCreateInfo {
+        message_severity: DebugUtilsMessageSeverity::all(),
+        message_type: DebugUtilsMessageType::all(),
+        ..DebugUtilsMessengerCreateInfo::user_callback(Arc::new(log))
+    }
+}
+
+/// Logs messages for as long as the instance is used, if it was created with the debug
+/// utils extension.
+pub fn messenger(instance: &Arc<Instance>) -> Option<DebugUtilsMessenger> {
+    if !instance.enabled_extensions().ext_debug_utils {
+        return None;
+    }
+
+    // Safe as `log` makes no Vulkan calls
+    match unsafe { DebugUtilsMessenger::new(instance.clone(), messenger_info()) } {
+        Ok(messenger) => Some(messenger),
+        Err(e) => {
+            warn!("Unable to create Vulkan debug messenger: {}", e);
+            None
+        }
+    }
+}
+
+/// Names an object in validation messages and graphics debuggers, if the debug utils
+/// extension is enabled.
+pub fn name<T: VulkanObject + DeviceOwned>(object: &T, name: &str) {
+    let device = object.device();
+
+    if !device.instance().enabled_extensions().ext_debug_utils {
+        return;
+    }
+
+    if let Err(e) = device.set_debug_utils_object_name(object, Some(name)) {
+        warn!("Unable to name {}: {}", name, e);
+    }
+}
+
+fn log(message: &Message) {
+    let kind = match message.ty {
+        ty if ty.validation => "validation",
+        ty if ty.performance => "performance",
+        _ => "general",
+    };
+
+    let layer = message.layer_prefix.unwrap_or("driver");
+    let description = message.description;
+
+    match message.severity {
+        severity if severity.error => error!(target: "vulkan", kind, layer, "{}", description),
+        severity if severity.warning => warn!(target: "vulkan", kind, layer, "{}", description),
+        severity if severity.information => {
+            debug!(target: "vulkan", kind, layer, "{}", description)
+        }
+        _ => trace!(target: "vulkan", kind, layer, "{}", description),
+    }
+}
@@ -0,0 +1,252 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+    sync::Arc,
+};
+
+use serde::Deserialize;
+use stateloop::app::Window;
+use vulkano::{
+    device::{
+        physical::{PhysicalDevice, PhysicalDeviceType, QueueFamily},
+        DeviceExtensions,
+    },
+    instance::Instance,
+    swapchain::Surface,
+};
+
+use super::InitError;
+
+/// Picks a physical device by its index in the enumeration order, or by a
+/// case-insensitive part of its name.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum DeviceSelector {
+    Index(usize),
+    Name(String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    MissingExtension(&'static str),
+    NoGraphicsQueue,
+    NoSurfaceSupport,
+}
+
+/// A device along with the queue family that would be used with it, or why it cannot be.
+pub struct Candidate<'a> {
+    pub device: PhysicalDevice<'a>,
+    pub suitability: Result<QueueFamily<'a>, Vec<Rejection>>,
+}
+
+pub struct DeviceDescription {
+    pub index: usize,
+    pub name: String,
+    pub device_type: PhysicalDeviceType,
+    pub api_version: String,
+    pub rejections: Vec<Rejection>,
+    pub preferred: bool,
+}
+
+pub fn required_extensions() -> DeviceExtensions {
+    DeviceExtensions {
+        khr_swapchain: true,
+        ..DeviceExtensions::none()
+    }
+}
+
+pub fn candidates<'a>(
+    instance: &'a Arc<Instance>,
+    surface: &Arc<Surface<Window>>,
+) -> Vec<Candidate<'a>> {
+    let required = required_extensions();
+
+    PhysicalDevice::enumerate(instance)
+        .map(|device| {
+            let mut rejections = Vec::new();
+
+            if !device.supported_extensions().is_superset_of(&required) {
+                rejections.push(Rejection::MissingExtension("VK_KHR_swapchain"));
+            }
+
+            let graphics = device
+                .queue_families()
+                .filter(|queue| queue.supports_graphics())
+                .collect::<Vec<_>>();
+
+            let queue = graphics
+                .iter()
+                .copied()
+                .find(|queue| queue.supports_surface(surface).unwrap_or(false));
+
+            if graphics.is_empty() {
+                rejections.push(Rejection::NoGraphicsQueue);
+            } else if queue.is_none() {
+                rejections.push(Rejection::NoSurfaceSupport);
+            }
+
+            let suitability = match queue {
+                Some(queue) if rejections.is_empty() => Ok(queue),
+                _ => Err(rejections),
+            };
+
+            Candidate {
+                device,
+                suitability,
+            }
+        })
+        .collect()
+}
+
+/// A queue family for transfers alone, which on most discrete GPUs copies through a
+/// dedicated engine alongside rendering.
+pub fn transfer_family(device: PhysicalDevice) -> Option<QueueFamily> {
+    device.queue_families().find(|queue| {
+        queue.explicitly_supports_transfers()
+            && !queue.supports_graphics()
+            && !queue.supports_compute()
+    })
+}
+
+/// Uses the selected device, or otherwise the most capable kind of suitable device.
+pub fn select<'a>(
+    candidates: Vec<Candidate<'a>>,
+    selector: Option<&DeviceSelector>,
+) -> Result<(PhysicalDevice<'a>, QueueFamily<'a>), InitError> {
+    match selector {
+        Some(selector) => {
+            let candidate = candidates
+                .into_iter()
+                .find(|candidate| selector.matches(&candidate.device))
+                .ok_or_else(|| InitError::DeviceNotFound(selector.clone()))?;
+
+            match candidate.suitability {
+                Ok(queue) => Ok((candidate.device, queue)),
+                Err(rejections) => Err(InitError::DeviceUnsuitable {
+                    name: candidate.device.properties().device_name.clone(),
+                    rejections,
+                }),
+            }
+        }
+        None => candidates
+            .into_iter()
+            .filter_map(|candidate| Some((candidate.device, candidate.suitability.ok()?)))
+            .min_by_key(|(device, _)| rank(device))
+            .ok_or(InitError::NoSuitableDeviceFound),
+    }
+}
+
+pub fn describe(candidates: &[Candidate]) -> Vec<DeviceDescription> {
+    let preferred = candidates
+        .iter()
+        .filter(|candidate| candidate.suitability.is_ok())
+        .min_by_key(|candidate| rank(&candidate.device))
+        .map(|candidate| candidate.device.index());
+
+    candidates
+        .iter()
+        .map(|candidate| {
+            let properties = candidate.device.properties();
+
+            DeviceDescription {
+                index: candidate.device.index(),
+                name: properties.device_name.clone(),
+                device_type: properties.device_type,
+                api_version: candidate.device.api_version().to_string(),
+                rejections: candidate.suitability.clone().err().unwrap_or_default(),
+                preferred: preferred == Some(candidate.device.index()),
+            }
+        })
+        .collect()
+}
+
+fn rank(device: &PhysicalDevice) -> u32 {
+    match device.properties().device_type {
+        PhysicalDeviceType::DiscreteGpu => 0,
+        PhysicalDeviceType::IntegratedGpu => 1,
+        PhysicalDeviceType::VirtualGpu => 2,
+        PhysicalDeviceType::Cpu => 3,
+        PhysicalDeviceType::Other => 4,
+    }
+}
+
+impl DeviceSelector {
+    fn matches(&self, device: &PhysicalDevice) -> bool {
+        match self {
+            DeviceSelector::Index(index) => device.index() == *index,
+            DeviceSelector::Name(name) => device
+                .properties()
+                .device_name
+                .to_lowercase()
+                .contains(&name.to_lowercase()),
+        }
+    }
+}
+
+impl FromStr for DeviceSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err("expected a device index or name".into()),
+            s => Ok(s
+                .parse()
+                .map_or_else(|_| DeviceSelector::Name(s.into()), DeviceSelector::Index)),
+        }
+    }
+}
+
+impl Display for DeviceSelector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceSelector::Index(index) => write!(f, "device {index}"),
+            DeviceSelector::Name(name) => write!(f, "device matching '{name}'"),
+        }
+    }
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::MissingExtension(extension) => write!(f, "missing {extension}"),
+            Rejection::NoGraphicsQueue => write!(f, "no graphics queue"),
+            Rejection::NoSurfaceSupport => write!(f, "no queue can present to the window"),
+        }
+    }
+}
+
+impl Display for DeviceDescription {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let device_type = match self.device_type {
+            PhysicalDeviceType::DiscreteGpu => "discrete GPU",
+            PhysicalDeviceType::IntegratedGpu => "integrated GPU",
+            PhysicalDeviceType::VirtualGpu => "virtual GPU",
+            PhysicalDeviceType::Cpu => "CPU",
+            PhysicalDeviceType::Other => "other",
+        };
+
+        write!(
+            f,
+            "{}: {} ({}, Vulkan {}) - ",
+            self.index, self.name, device_type, self.api_version
+        )?;
+
+        match self.rejections.as_slice() {
+            [] if self.preferred => write!(f, "accepted, default"),
+            [] => write!(f, "accepted"),
+            rejections => {
+                write!(f, "rejected: ")?;
+
+                for (index, rejection) in rejections.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{rejection}")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
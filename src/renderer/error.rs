@@ -0,0 +1,378 @@
+use std::{
+    error::Error,
+    ffi::CString,
+    fmt::{self, Display, Formatter},
+};
+
+use vulkano::OomError;
+use vulkano::{
+    buffer::{cpu_access::ReadLockError, immutable::ImmutableBufferCreationError},
+    command_buffer::{
+        BuildError, CommandBufferBeginError, CommandBufferExecError, DrawError, DrawIndexedError,
+        RenderPassError,
+    },
+    descriptor_set::DescriptorSetCreationError,
+    device::{physical::SurfacePropertiesError, DeviceCreationError},
+    image::{immutable::ImmutableImageCreationError, view::ImageViewCreationError},
+    instance::InstanceCreationError,
+    memory::DeviceMemoryAllocationError,
+    pipeline::graphics::GraphicsPipelineCreationError,
+    render_pass::{FramebufferCreationError, RenderPassCreationError},
+    shader::ShaderCreationError,
+    swapchain::{AcquireError, SwapchainCreationError},
+    sync::FlushError,
+};
+use vulkano_win::CreationError;
+
+use super::{DeviceSelector, Rejection};
+
+#[derive(Debug)]
+pub enum InitError {
+    UnableToCreateInstance {
+        extensions: Vec<String>,
+        error: InstanceCreationError,
+    },
+    UnableToCreateWindow(CreationError),
+    NoSuitableDeviceFound,
+    DeviceNotFound(DeviceSelector),
+    DeviceUnsuitable {
+        name: String,
+        rejections: Vec<Rejection>,
+    },
+    UnableToCreateDevice {
+        name: String,
+        extensions: Vec<String>,
+        error: DeviceCreationError,
+    },
+    UnableToGetSurfaceCapabilities(SurfacePropertiesError),
+    UnableToGetSurfaceFormats(SurfacePropertiesError),
+    UnableToGetPresentModes(SurfacePropertiesError),
+    NoSurfaceFormats,
+    UnableToCreateSwapchain(SwapchainCreationError),
+    UnableToCreateRenderPass(RenderPassCreationError),
+    UnableToCreatePipeline(GraphicsPipelineCreationError),
+    UnableToLoadShaders(ShaderCreationError),
+    UnableToCreateVertexBuffer(ImmutableBufferCreationError),
+    UnableToCreateGlyphAtlas(ImmutableImageCreationError),
+    ChunkTooLarge {
+        name: String,
+        chunk_size: u32,
+        max_size: u32,
+    },
+}
+
+impl Display for InitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InitError::UnableToCreateInstance { extensions, .. } => {
+                write!(f, "unable to initialise Vulkan")?;
+                write_extensions(f, extensions)
+            }
+            InitError::UnableToCreateWindow(_) => write!(f, "unable to create window"),
+            InitError::NoSuitableDeviceFound => write!(
+                f,
+                "no graphics device can be used, see --list-devices for the reasons"
+            ),
+            InitError::DeviceNotFound(selector) => write!(
+                f,
+                "there is no {}, see --list-devices for those available",
+                selector
+            ),
+            InitError::DeviceUnsuitable { name, rejections } => {
+                write!(f, "{} cannot be used: ", name)?;
+
+                for (index, rejection) in rejections.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{}", rejection)?;
+                }
+
+                Ok(())
+            }
+            InitError::UnableToCreateDevice {
+                name, extensions, ..
+            } => {
+                write!(f, "unable to create device on {}", name)?;
+                write_extensions(f, extensions)
+            }
+            InitError::UnableToGetSurfaceCapabilities(_) => {
+                write!(f, "unable to get surface capabilities")
+            }
+            InitError::UnableToGetSurfaceFormats(_) => write!(f, "unable to get surface formats"),
+            InitError::UnableToGetPresentModes(_) => write!(f, "unable to get present modes"),
+            InitError::NoSurfaceFormats => write!(f, "the surface supports no formats"),
+            InitError::UnableToCreateSwapchain(_) => write!(f, "unable to create swapchain"),
+            InitError::UnableToCreateRenderPass(_) => write!(f, "unable to create render pass"),
+            InitError::UnableToCreatePipeline(_) => write!(f, "unable to create pipeline"),
+            InitError::UnableToLoadShaders(_) => write!(f, "unable to load shaders"),
+            InitError::UnableToCreateVertexBuffer(_) => write!(f, "unable to create vertex buffer"),
+            InitError::UnableToCreateGlyphAtlas(_) => write!(f, "unable to create glyph atlas"),
+            InitError::ChunkTooLarge {
+                name,
+                chunk_size,
+                max_size,
+            } => write!(
+                f,
+                "{chunk_size}x{chunk_size} chunk textures are larger than the \
+                 {max_size}x{max_size} limit of {name}"
+            ),
+        }
+    }
+}
+
+impl Error for InitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InitError::UnableToCreateInstance { error, .. } => Some(error),
+            InitError::UnableToCreateWindow(e) => Some(e),
+            InitError::UnableToCreateDevice { error, .. } => Some(error),
+            InitError::UnableToGetSurfaceCapabilities(e)
+            | InitError::UnableToGetSurfaceFormats(e)
+            | InitError::UnableToGetPresentModes(e) => Some(e),
+            InitError::UnableToCreateSwapchain(e) => Some(e),
+            InitError::UnableToCreateRenderPass(e) => Some(e),
+            InitError::UnableToCreatePipeline(e) => Some(e),
+            InitError::UnableToLoadShaders(e) => Some(e),
+            InitError::UnableToCreateVertexBuffer(e) => Some(e),
+            InitError::UnableToCreateGlyphAtlas(e) => Some(e),
+            InitError::NoSuitableDeviceFound
+            | InitError::DeviceNotFound(_)
+            | InitError::DeviceUnsuitable { .. }
+            | InitError::NoSurfaceFormats
+            | InitError::ChunkTooLarge { .. } => None,
+        }
+    }
+}
+
+pub fn extension_names<E>(extensions: &E) -> Vec<String>
+where
+    for<'a> &'a E: Into<Vec<CString>>,
+{
+    extensions
+        .into()
+        .into_iter()
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect()
+}
+
+fn write_extensions(f: &mut Formatter<'_>, extensions: &[String]) -> fmt::Result {
+    match extensions {
+        [] => Ok(()),
+        extensions => write!(f, " with extensions {}", extensions.join(", ")),
+    }
+}
+
+#[derive(Debug)]
+pub enum RenderError {
+    /// The device has stopped responding, taking everything created with it.
+    DeviceLost,
+    /// The window's surface can no longer be presented to.
+    SurfaceLost,
+    UnableToGetSurfaceCapabilities(SurfacePropertiesError),
+    UnableToRecreateSwapchain(SwapchainCreationError),
+    UnableToAcquireImage(AcquireError),
+    UnableToCreateTexture(ImmutableImageCreationError),
+    UnableToCreateImageView(ImageViewCreationError),
+    UnableToCreateFramebuffer(FramebufferCreationError),
+    UnableToAllocateBuffer(DeviceMemoryAllocationError),
+    UnableToReadBuffer(ReadLockError),
+    UnableToCheckUpload(OomError),
+    UnableToCreateDescriptorSet(DescriptorSetCreationError),
+    UnableToBeginCommandBuffer(CommandBufferBeginError),
+    UnableToRecordRenderPass(RenderPassError),
+    UnableToDraw(DrawError),
+    UnableToDrawIndexed(DrawIndexedError),
+    UnableToBuildCommandBuffer(BuildError),
+    UnableToExecute(CommandBufferExecError),
+    UnableToPresent(FlushError),
+}
+
+impl RenderError {
+    /// Whether rebuilding the renderer could get things going again. A lost surface
+    /// cannot be replaced, as the window it was created from belongs to it.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, RenderError::DeviceLost)
+    }
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::DeviceLost => write!(f, "the graphics device was lost"),
+            RenderError::SurfaceLost => write!(f, "the window surface was lost"),
+            RenderError::UnableToGetSurfaceCapabilities(_) => {
+                write!(f, "unable to get surface capabilities")
+            }
+            RenderError::UnableToRecreateSwapchain(_) => {
+                write!(f, "unable to recreate the swapchain")
+            }
+            RenderError::UnableToAcquireImage(_) => {
+                write!(f, "unable to acquire a swapchain image")
+            }
+            RenderError::UnableToCreateTexture(_) => write!(f, "unable to create texture"),
+            RenderError::UnableToCreateImageView(_) => write!(f, "unable to create image view"),
+            RenderError::UnableToCreateFramebuffer(_) => write!(f, "unable to create framebuffer"),
+            RenderError::UnableToAllocateBuffer(_) => write!(f, "unable to allocate buffer"),
+            RenderError::UnableToReadBuffer(_) => write!(f, "unable to read buffer"),
+            RenderError::UnableToCheckUpload(_) => write!(f, "unable to check upload progress"),
+            RenderError::UnableToCreateDescriptorSet(_) => {
+                write!(f, "unable to create descriptor set")
+            }
+            RenderError::UnableToBeginCommandBuffer(_) => {
+                write!(f, "unable to begin command buffer")
+            }
+            RenderError::UnableToRecordRenderPass(_) => write!(f, "unable to record render pass"),
+            RenderError::UnableToDraw(_) => write!(f, "unable to record draw"),
+            RenderError::UnableToDrawIndexed(_) => write!(f, "unable to record indexed draw"),
+            RenderError::UnableToBuildCommandBuffer(_) => {
+                write!(f, "unable to build command buffer")
+            }
+            RenderError::UnableToExecute(_) => write!(f, "unable to execute commands"),
+            RenderError::UnableToPresent(_) => write!(f, "unable to present frame"),
+        }
+    }
+}
+
+impl Error for RenderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RenderError::DeviceLost | RenderError::SurfaceLost => None,
+            RenderError::UnableToGetSurfaceCapabilities(e) => Some(e),
+            RenderError::UnableToRecreateSwapchain(e) => Some(e),
+            RenderError::UnableToAcquireImage(e) => Some(e),
+            RenderError::UnableToCreateTexture(e) => Some(e),
+            RenderError::UnableToCreateImageView(e) => Some(e),
+            RenderError::UnableToCreateFramebuffer(e) => Some(e),
+            RenderError::UnableToAllocateBuffer(e) => Some(e),
+            RenderError::UnableToReadBuffer(e) => Some(e),
+            RenderError::UnableToCheckUpload(e) => Some(e),
+            RenderError::UnableToCreateDescriptorSet(e) => Some(e),
+            RenderError::UnableToBeginCommandBuffer(e) => Some(e),
+            RenderError::UnableToRecordRenderPass(e) => Some(e),
+            RenderError::UnableToDraw(e) => Some(e),
+            RenderError::UnableToDrawIndexed(e) => Some(e),
+            RenderError::UnableToBuildCommandBuffer(e) => Some(e),
+            RenderError::UnableToExecute(e) => Some(e),
+            RenderError::UnableToPresent(e) => Some(e),
+        }
+    }
+}
+
+impl From<SurfacePropertiesError> for RenderError {
+    fn from(e: SurfacePropertiesError) -> Self {
+        match e {
+            SurfacePropertiesError::SurfaceLost => RenderError::SurfaceLost,
+            e => RenderError::UnableToGetSurfaceCapabilities(e),
+        }
+    }
+}
+
+impl From<SwapchainCreationError> for RenderError {
+    fn from(e: SwapchainCreationError) -> Self {
+        match e {
+            SwapchainCreationError::DeviceLost => RenderError::DeviceLost,
+            SwapchainCreationError::SurfaceLost => RenderError::SurfaceLost,
+            e => RenderError::UnableToRecreateSwapchain(e),
+        }
+    }
+}
+
+impl From<AcquireError> for RenderError {
+    fn from(e: AcquireError) -> Self {
+        match e {
+            AcquireError::DeviceLost => RenderError::DeviceLost,
+            AcquireError::SurfaceLost => RenderError::SurfaceLost,
+            e => RenderError::UnableToAcquireImage(e),
+        }
+    }
+}
+
+impl From<FlushError> for RenderError {
+    fn from(e: FlushError) -> Self {
+        match e {
+            FlushError::DeviceLost => RenderError::DeviceLost,
+            FlushError::SurfaceLost => RenderError::SurfaceLost,
+            e => RenderError::UnableToPresent(e),
+        }
+    }
+}
+
+impl From<ImmutableImageCreationError> for RenderError {
+    fn from(e: ImmutableImageCreationError) -> Self {
+        RenderError::UnableToCreateTexture(e)
+    }
+}
+
+impl From<ImageViewCreationError> for RenderError {
+    fn from(e: ImageViewCreationError) -> Self {
+        RenderError::UnableToCreateImageView(e)
+    }
+}
+
+impl From<FramebufferCreationError> for RenderError {
+    fn from(e: FramebufferCreationError) -> Self {
+        RenderError::UnableToCreateFramebuffer(e)
+    }
+}
+
+impl From<DeviceMemoryAllocationError> for RenderError {
+    fn from(e: DeviceMemoryAllocationError) -> Self {
+        RenderError::UnableToAllocateBuffer(e)
+    }
+}
+
+impl From<ReadLockError> for RenderError {
+    fn from(e: ReadLockError) -> Self {
+        RenderError::UnableToReadBuffer(e)
+    }
+}
+
+impl From<OomError> for RenderError {
+    fn from(e: OomError) -> Self {
+        RenderError::UnableToCheckUpload(e)
+    }
+}
+
+impl From<DescriptorSetCreationError> for RenderError {
+    fn from(e: DescriptorSetCreationError) -> Self {
+        RenderError::UnableToCreateDescriptorSet(e)
+    }
+}
+
+impl From<CommandBufferBeginError> for RenderError {
+    fn from(e: CommandBufferBeginError) -> Self {
+        RenderError::UnableToBeginCommandBuffer(e)
+    }
+}
+
+impl From<RenderPassError> for RenderError {
+    fn from(e: RenderPassError) -> Self {
+        RenderError::UnableToRecordRenderPass(e)
+    }
+}
+
+impl From<DrawError> for RenderError {
+    fn from(e: DrawError) -> Self {
+        RenderError::UnableToDraw(e)
+    }
+}
+
+impl From<DrawIndexedError> for RenderError {
+    fn from(e: DrawIndexedError) -> Self {
+        RenderError::UnableToDrawIndexed(e)
+    }
+}
+
+impl From<BuildError> for RenderError {
+    fn from(e: BuildError) -> Self {
+        RenderError::UnableToBuildCommandBuffer(e)
+    }
+}
+
+impl From<CommandBufferExecError> for RenderError {
+    fn from(e: CommandBufferExecError) -> Self {
+        RenderError::UnableToExecute(e)
+    }
+}
@@ -1,6 +1,7 @@
 use stateloop::winit::dpi::LogicalSize;
 use std::{marker::PhantomData, sync::Arc};
 use vulkano::{
+    buffer::BufferAccess,
     command_buffer::{
         pool::standard::{StandardCommandPoolAlloc, StandardCommandPoolBuilder},
         AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer,
@@ -8,15 +9,39 @@ use vulkano::{
     },
     descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
     image::ImageViewAbstract,
-    pipeline::{Pipeline, PipelineBindPoint},
+    pipeline::{graphics::viewport::Viewport, Pipeline, PipelineBindPoint},
 };
 
-use super::{shaders::MeshData, RendererData};
+use super::{
+    gui::GuiBatch, primitive::PrimitiveBatch, shaders::MeshData, text::TextBatch, RenderError,
+    RendererData,
+};
 
 pub mod frame_state {
     pub struct Begin;
     pub struct RenderPass;
+    pub struct Inset;
     pub struct Done;
+
+    /// States in which the render pass is open and can be drawn into.
+    pub trait Drawing {}
+
+    impl Drawing for RenderPass {}
+    impl Drawing for Inset {}
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Bound {
+    Chunks,
+    Primitives,
+    Text,
+    Gui,
+}
+
+/// An area being drawn into, along with its scene uniform.
+struct Target {
+    viewport: Viewport,
+    scene: Arc<dyn BufferAccess>,
 }
 
 pub struct RenderFrame<'data, State> {
@@ -26,136 +51,369 @@ pub struct RenderFrame<'data, State> {
         PrimaryAutoCommandBuffer<StandardCommandPoolAlloc>,
         StandardCommandPoolBuilder,
     >,
+    window_size: LogicalSize<f32>,
+    targets: Vec<Target>,
+    bound: Option<Bound>,
+    /// The first command that failed to record, after which the rest are skipped.
+    error: Option<RenderError>,
     _marker: PhantomData<State>,
 }
 
+impl<'data, State> RenderFrame<'data, State> {
+    fn into_state<Next>(self) -> RenderFrame<'data, Next> {
+        RenderFrame {
+            data: self.data,
+            builder: self.builder,
+            image_num: self.image_num,
+            window_size: self.window_size,
+            targets: self.targets,
+            bound: self.bound,
+            error: self.error,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Runs a step of recording unless an earlier one has failed, keeping the first error
+    /// so that drawing calls can still be chained.
+    fn record<F>(&mut self, step: F)
+    where
+        F: FnOnce(&mut Self) -> Result<(), RenderError>,
+    {
+        if self.error.is_none() {
+            if let Err(e) = step(self) {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn push_target(&mut self, viewport: Viewport, size: LogicalSize<f32>) {
+        self.record(|frame| {
+            let scene = frame.data.uniform_buffer.next(size.into())?;
+
+            frame.builder.set_viewport(0, [viewport.clone()]);
+            frame.targets.push(Target { viewport, scene });
+            frame.bound = None;
+            Ok(())
+        });
+    }
+
+    fn pop_target(&mut self) {
+        self.record(|frame| {
+            frame.targets.pop();
+
+            let target = frame.targets.last().unwrap();
+            frame.builder.set_viewport(0, [target.viewport.clone()]);
+            frame.bound = None;
+            Ok(())
+        });
+    }
+}
+
 impl<'data> RenderFrame<'data, frame_state::Begin> {
-    pub fn new(data: &'data mut RendererData, image_num: usize) -> Self {
+    pub fn new(data: &'data mut RendererData, image_num: usize) -> Result<Self, RenderError> {
         let builder = AutoCommandBufferBuilder::primary(
             data.objects.device.clone(),
             data.objects.queue.family(),
             CommandBufferUsage::OneTimeSubmit,
-        )
-        .unwrap();
+        )?;
 
-        Self {
+        Ok(Self {
             data,
             image_num,
             builder,
+            window_size: LogicalSize::new(0.0, 0.0),
+            targets: Vec::new(),
+            bound: None,
+            error: None,
             _marker: PhantomData,
-        }
+        })
     }
 
     pub fn begin(
         mut self,
         window_size: LogicalSize<f32>,
     ) -> RenderFrame<'data, frame_state::RenderPass> {
-        let uniform_buffer = self.data.uniform_buffer.next(window_size.into()).unwrap();
-
-        let descriptor_set = PersistentDescriptorSet::new(
-            self.data
-                .pipeline
-                .layout()
-                .set_layouts()
-                .get(0)
-                .unwrap()
-                .clone(),
-            [WriteDescriptorSet::buffer(0, uniform_buffer)],
-        )
-        .unwrap();
-
-        self.builder
-            .begin_render_pass(
+        self.record(|frame| {
+            frame.builder.begin_render_pass(
                 RenderPassBeginInfo {
                     clear_values: vec![Some([1.0, 0.0, 1.0, 1.0].into())],
                     ..RenderPassBeginInfo::framebuffer(
-                        self.data.framebuffers.as_ref().unwrap()[self.image_num].clone(),
+                        frame.data.framebuffers.as_ref().unwrap()[frame.image_num].clone(),
                     )
                 },
                 SubpassContents::Inline,
-            )
-            .unwrap()
-            .set_viewport(0, [self.data.viewport.clone()])
-            .bind_pipeline_graphics(self.data.pipeline.clone())
-            .bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                self.data.pipeline.layout().clone(),
-                0,
-                descriptor_set,
-            )
-            .bind_vertex_buffers(0, self.data.vertex_buffer.clone());
+            )?;
+            Ok(())
+        });
 
-        RenderFrame {
-            data: self.data,
-            builder: self.builder,
-            image_num: self.image_num,
-            _marker: PhantomData,
-        }
+        self.window_size = window_size;
+        self.push_target(self.data.viewport.clone(), window_size);
+        self.into_state()
     }
 }
 
 impl<'data> RenderFrame<'data, frame_state::RenderPass> {
-    pub fn draw(mut self, position: LogicalSize<f32>, texture: Arc<dyn ImageViewAbstract>) -> Self {
-        let descriptor_set = PersistentDescriptorSet::new(
-            self.data
-                .pipeline
-                .layout()
-                .set_layouts()
-                .get(1)
-                .unwrap()
-                .clone(),
-            [WriteDescriptorSet::image_view_sampler(
-                0,
-                texture,
-                self.data.sampler.clone(),
-            )],
-        )
-        .unwrap();
+    /// Restricts drawing to a rectangle of the window, given in logical pixels. Positions
+    /// inside the inset are relative to its top-left corner.
+    pub fn inset(
+        mut self,
+        position: [f32; 2],
+        size: [f32; 2],
+    ) -> RenderFrame<'data, frame_state::Inset> {
+        let window = &self.data.viewport;
+        let scale = [
+            window.dimensions[0] / self.window_size.width,
+            window.dimensions[1] / self.window_size.height,
+        ];
 
-        self.builder
-            .push_constants(
-                self.data.pipeline.layout().clone(),
-                0,
-                MeshData {
-                    offset: position.into(),
-                },
-            )
-            .bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                self.data.pipeline.layout().clone(),
-                1,
-                descriptor_set,
-            )
-            .draw(4, 1, 0, 0)
-            .unwrap();
+        let viewport = Viewport {
+            origin: [
+                window.origin[0] + position[0] * scale[0],
+                window.origin[1] + position[1] * scale[1],
+            ],
+            dimensions: [size[0] * scale[0], size[1] * scale[1]],
+            depth_range: window.depth_range.clone(),
+        };
 
-        RenderFrame {
-            data: self.data,
-            builder: self.builder,
-            image_num: self.image_num,
-            _marker: PhantomData,
+        self.push_target(viewport, LogicalSize::new(size[0], size[1]));
+        self.into_state()
+    }
+
+    /// Draws an egui frame over the whole window, uploading any textures it needs first.
+    pub fn gui(mut self, batch: &GuiBatch) -> Self {
+        if batch.primitives.is_empty() {
+            return self;
         }
+
+        self.record(|frame| {
+            let layout = frame.data.gui_pipeline.layout().clone();
+            let uploads = frame.data.gui_textures.upload(
+                batch.textures(),
+                &frame.data.objects.queue,
+                &layout.set_layouts()[1],
+                &frame.data.sampler,
+            )?;
+
+            for upload in uploads {
+                frame.data.chain(upload);
+            }
+
+            frame.bind(Bound::Gui)?;
+
+            let window = frame.data.viewport.dimensions;
+            let scale = [
+                window[0] / frame.window_size.width,
+                window[1] / frame.window_size.height,
+            ];
+
+            for mesh in batch.meshes() {
+                let texture = frame.data.gui_textures.get(mesh.texture);
+                let (Some(texture), Some(scissor)) = (texture, mesh.scissor(scale, window)) else {
+                    continue;
+                };
+
+                let count = mesh.indices.len() as u32;
+                let vertices = frame.data.overlay_vertex_buffer.chunk(mesh.vertices)?;
+                let indices = frame.data.gui_index_buffer.chunk(mesh.indices)?;
+
+                frame
+                    .builder
+                    .set_scissor(0, [scissor])
+                    .bind_descriptor_sets(PipelineBindPoint::Graphics, layout.clone(), 1, texture)
+                    .bind_vertex_buffers(0, vertices)
+                    .bind_index_buffer(indices)
+                    .draw_indexed(count, 1, 0, 0, 0)?;
+            }
+
+            Ok(())
+        });
+
+        self
     }
 
     pub fn finish(mut self) -> RenderFrame<'data, frame_state::Done> {
-        self.builder.end_render_pass().unwrap();
+        self.record(|frame| {
+            frame.builder.end_render_pass()?;
+            Ok(())
+        });
 
-        RenderFrame {
-            data: self.data,
-            builder: self.builder,
-            image_num: self.image_num,
-            _marker: PhantomData,
+        self.into_state()
+    }
+}
+
+impl<'data> RenderFrame<'data, frame_state::Inset> {
+    pub fn end_inset(mut self) -> RenderFrame<'data, frame_state::RenderPass> {
+        self.pop_target();
+        self.into_state()
+    }
+}
+
+impl<'data, State: frame_state::Drawing> RenderFrame<'data, State> {
+    /// Draws a chunk texture as a square `size` logical pixels across.
+    pub fn draw(
+        mut self,
+        position: LogicalSize<f32>,
+        size: f32,
+        texture: Arc<dyn ImageViewAbstract>,
+    ) -> Self {
+        self.record(|frame| {
+            frame.bind(Bound::Chunks)?;
+
+            let descriptor_set = PersistentDescriptorSet::new(
+                frame
+                    .data
+                    .pipeline
+                    .layout()
+                    .set_layouts()
+                    .get(1)
+                    .unwrap()
+                    .clone(),
+                [WriteDescriptorSet::image_view_sampler(
+                    0,
+                    texture,
+                    frame.data.sampler.clone(),
+                )],
+            )?;
+
+            frame
+                .builder
+                .push_constants(
+                    frame.data.pipeline.layout().clone(),
+                    0,
+                    MeshData {
+                        offset: position.into(),
+                        size,
+                    },
+                )
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    frame.data.pipeline.layout().clone(),
+                    1,
+                    descriptor_set,
+                )
+                .draw(4, 1, 0, 0)?;
+
+            Ok(())
+        });
+
+        self
+    }
+
+    pub fn primitives(mut self, batch: &PrimitiveBatch) -> Self {
+        if batch.vertices().is_empty() {
+            return self;
         }
+
+        self.record(|frame| {
+            frame.bind(Bound::Primitives)?;
+
+            let vertices = frame
+                .data
+                .primitive_vertex_buffer
+                .chunk(batch.vertices().iter().cloned())?;
+
+            frame.builder.bind_vertex_buffers(0, vertices).draw(
+                batch.vertices().len() as u32,
+                1,
+                0,
+                0,
+            )?;
+
+            Ok(())
+        });
+
+        self
+    }
+
+    pub fn text(mut self, batch: &TextBatch) -> Self {
+        if batch.vertices().is_empty() {
+            return self;
+        }
+
+        self.record(|frame| {
+            frame.bind(Bound::Text)?;
+
+            let vertices = frame
+                .data
+                .overlay_vertex_buffer
+                .chunk(batch.vertices().iter().cloned())?;
+
+            frame.builder.bind_vertex_buffers(0, vertices).draw(
+                batch.vertices().len() as u32,
+                1,
+                0,
+                0,
+            )?;
+
+            Ok(())
+        });
+
+        self
+    }
+
+    /// Binds the pipeline and scene for a kind of draw, unless they are already bound.
+    fn bind(&mut self, kind: Bound) -> Result<(), RenderError> {
+        if self.bound == Some(kind) {
+            return Ok(());
+        }
+
+        let pipeline = match kind {
+            Bound::Chunks => self.data.pipeline.clone(),
+            Bound::Primitives => self.data.primitive_pipeline.clone(),
+            Bound::Text => self.data.overlay_pipeline.clone(),
+            Bound::Gui => self.data.gui_pipeline.clone(),
+        };
+
+        let scene = PersistentDescriptorSet::new(
+            pipeline.layout().set_layouts().first().unwrap().clone(),
+            [WriteDescriptorSet::buffer(
+                0,
+                self.targets.last().unwrap().scene.clone(),
+            )],
+        )?;
+
+        let layout = pipeline.layout().clone();
+        self.builder.bind_pipeline_graphics(pipeline);
+
+        match kind {
+            Bound::Chunks => {
+                self.builder
+                    .bind_descriptor_sets(PipelineBindPoint::Graphics, layout, 0, scene)
+                    .bind_vertex_buffers(0, self.data.vertex_buffer.clone());
+            }
+            Bound::Primitives | Bound::Gui => {
+                self.builder
+                    .bind_descriptor_sets(PipelineBindPoint::Graphics, layout, 0, scene);
+            }
+            Bound::Text => {
+                self.builder.bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    layout,
+                    0,
+                    (scene, self.data.glyph_atlas.clone()),
+                );
+            }
+        }
+
+        self.bound = Some(kind);
+        Ok(())
     }
 }
 
 impl<'data> RenderFrame<'data, frame_state::Done> {
-    pub fn unwrap(
+    /// The recorded commands, or the first error hit while recording them.
+    pub fn into_builder(
         self,
-    ) -> AutoCommandBufferBuilder<
-        PrimaryAutoCommandBuffer<StandardCommandPoolAlloc>,
-        StandardCommandPoolBuilder,
+    ) -> Result<
+        AutoCommandBufferBuilder<
+            PrimaryAutoCommandBuffer<StandardCommandPoolAlloc>,
+            StandardCommandPoolBuilder,
+        >,
+        RenderError,
     > {
-        self.builder
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.builder),
+        }
     }
 }
@@ -0,0 +1,200 @@
+use std::{collections::HashMap, sync::Arc};
+
+use egui::{epaint::Primitive, ClippedPrimitive, ColorImage, ImageData, TextureId, TexturesDelta};
+use vulkano::{
+    command_buffer::{CommandBufferExecFuture, PrimaryAutoCommandBuffer},
+    descriptor_set::{layout::DescriptorSetLayout, PersistentDescriptorSet, WriteDescriptorSet},
+    device::Queue,
+    format::Format,
+    image::{view::ImageView, ImageAccess, ImageDimensions, ImmutableImage, MipmapsCount},
+    pipeline::graphics::viewport::Scissor,
+    sampler::Sampler,
+    sync::NowFuture,
+};
+
+use super::{debug, vertex::OverlayVertex, RenderError};
+
+type Upload = CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>;
+
+/// An egui frame, tessellated into meshes positioned in logical pixels.
+#[derive(Default)]
+pub struct GuiBatch {
+    pub primitives: Vec<ClippedPrimitive>,
+}
+
+/// A mesh ready for the GPU, clipped to a rectangle of the window.
+pub struct GuiMesh {
+    pub texture: TextureId,
+    pub vertices: Vec<OverlayVertex>,
+    pub indices: Vec<u32>,
+    pub clip: [[f32; 2]; 2],
+}
+
+/// The images egui draws with. They are kept in memory as well as on the GPU, as egui
+/// only sends changes, and everything has to be uploaded again if the device is lost.
+#[derive(Default)]
+pub struct GuiTextures {
+    images: HashMap<TextureId, ColorImage>,
+    uploaded: HashMap<TextureId, Arc<PersistentDescriptorSet>>,
+}
+
+impl GuiBatch {
+    pub fn textures(&self) -> impl Iterator<Item = TextureId> + '_ {
+        self.primitives
+            .iter()
+            .filter_map(|clipped| match &clipped.primitive {
+                Primitive::Mesh(mesh) => Some(mesh.texture_id),
+                Primitive::Callback(_) => None,
+            })
+    }
+
+    pub fn meshes(&self) -> impl Iterator<Item = GuiMesh> + '_ {
+        self.primitives.iter().filter_map(|clipped| {
+            let Primitive::Mesh(mesh) = &clipped.primitive else {
+                return None;
+            };
+
+            if mesh.indices.is_empty() {
+                return None;
+            }
+
+            // egui colours are premultiplied in gamma space, and blended in linear space here
+            let vertices = mesh
+                .vertices
+                .iter()
+                .map(|vertex| {
+                    let colour = egui::Rgba::from(vertex.color);
+                    OverlayVertex::new(vertex.pos.into(), vertex.uv.into(), colour.to_array())
+                })
+                .collect();
+
+            Some(GuiMesh {
+                texture: mesh.texture_id,
+                vertices,
+                indices: mesh.indices.clone(),
+                clip: [clipped.clip_rect.min.into(), clipped.clip_rect.max.into()],
+            })
+        })
+    }
+}
+
+impl GuiMesh {
+    /// The clip rectangle in physical pixels, or `None` if nothing would be drawn.
+    pub fn scissor(&self, scale: [f32; 2], extent: [f32; 2]) -> Option<Scissor> {
+        let [[left, top], [right, bottom]] = self.clip;
+        let min = [
+            (left * scale[0]).clamp(0.0, extent[0]).round(),
+            (top * scale[1]).clamp(0.0, extent[1]).round(),
+        ];
+        let max = [
+            (right * scale[0]).clamp(min[0], extent[0]).round(),
+            (bottom * scale[1]).clamp(min[1], extent[1]).round(),
+        ];
+
+        if max[0] <= min[0] || max[1] <= min[1] {
+            return None;
+        }
+
+        Some(Scissor {
+            origin: [min[0] as u32, min[1] as u32],
+            dimensions: [(max[0] - min[0]) as u32, (max[1] - min[1]) as u32],
+        })
+    }
+}
+
+impl GuiTextures {
+    pub fn update(&mut self, delta: TexturesDelta) {
+        for (id, delta) in delta.set {
+            let ImageData::Color(image) = delta.image;
+
+            match (delta.pos, self.images.get_mut(&id)) {
+                (Some([x, y]), Some(existing)) => {
+                    let width = existing.size[0];
+
+                    for (row, pixels) in image.pixels.chunks_exact(image.size[0]).enumerate() {
+                        let start = (y + row) * width + x;
+                        existing.pixels[start..start + pixels.len()].copy_from_slice(pixels);
+                    }
+                }
+                _ => {
+                    self.images.insert(id, (*image).clone());
+                }
+            }
+
+            self.uploaded.remove(&id);
+        }
+
+        for id in delta.free {
+            self.images.remove(&id);
+            self.uploaded.remove(&id);
+        }
+    }
+
+    /// The images without anything uploaded from them, for a device that has been rebuilt.
+    pub fn lost(self) -> Self {
+        Self {
+            images: self.images,
+            uploaded: HashMap::new(),
+        }
+    }
+
+    /// Uploads the textures in `ids` that are not on the GPU yet, returning the uploads
+    /// for drawing to wait on.
+    pub fn upload<I>(
+        &mut self,
+        ids: I,
+        queue: &Arc<Queue>,
+        layout: &Arc<DescriptorSetLayout>,
+        sampler: &Arc<Sampler>,
+    ) -> Result<Vec<Upload>, RenderError>
+    where
+        I: IntoIterator<Item = TextureId>,
+    {
+        let mut uploads = Vec::new();
+
+        for id in ids {
+            let image = match self.images.get(&id) {
+                Some(image) if !self.uploaded.contains_key(&id) => image,
+                _ => continue,
+            };
+
+            let texels = image
+                .pixels
+                .iter()
+                .flat_map(|pixel| pixel.to_array())
+                .collect::<Vec<_>>();
+
+            let (texture, upload) = ImmutableImage::from_iter(
+                texels,
+                ImageDimensions::Dim2d {
+                    width: image.size[0] as u32,
+                    height: image.size[1] as u32,
+                    array_layers: 1,
+                },
+                MipmapsCount::One,
+                Format::R8G8B8A8_SRGB,
+                queue.clone(),
+            )?;
+
+            debug::name(&**texture.inner().image, &format!("gui texture {:?}", id));
+
+            let set = PersistentDescriptorSet::new(
+                layout.clone(),
+                [WriteDescriptorSet::image_view_sampler(
+                    0,
+                    ImageView::new_default(texture)?,
+                    sampler.clone(),
+                )],
+            )?;
+
+            self.uploaded.insert(id, set);
+            uploads.push(upload);
+        }
+
+        Ok(uploads)
+    }
+
+    pub fn get(&self, id: TextureId) -> Option<Arc<PersistentDescriptorSet>> {
+        self.uploaded.get(&id).cloned()
+    }
+}
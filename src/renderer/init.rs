@@ -4,91 +4,142 @@ use stateloop::{
     app::{EventLoop, Window, WindowBuilder},
     winit::dpi::LogicalSize,
 };
+use tracing::info;
 use vulkano::{
-    device::{
-        physical::{PhysicalDevice, PhysicalDeviceType},
-        Device, DeviceCreateInfo, DeviceExtensions, QueueCreateInfo,
-    },
+    device::{Device, DeviceCreateInfo, QueueCreateInfo},
     image::ImageUsage,
-    instance::Instance,
+    instance::{Instance, InstanceCreateInfo},
     swapchain::{Surface, Swapchain, SwapchainCreateInfo},
 };
-use vulkano_win::{CreationError, VkSurfaceBuild};
+use vulkano_win::VkSurfaceBuild;
 
-use super::{CoreObjects, InitError};
+use super::{debug, device, error, swapchain, CoreObjects, InitError, RendererSettings};
 
-pub fn construct_window(
-    event_loop: &EventLoop<()>,
+pub fn create_instance(debug: bool) -> Result<Arc<Instance>, InitError> {
+    let mut extensions = vulkano_win::required_extensions();
+    let mut layers = Vec::new();
+
+    if debug {
+        extensions = extensions.union(&debug::extensions());
+        layers = debug::layers();
+    }
+
+    let create_info = InstanceCreateInfo {
+        enabled_extensions: extensions,
+        enabled_layers: layers,
+        enumerate_portability: true,
+        ..Default::default()
+    };
+
+    let messengers = extensions
+        .ext_debug_utils
+        .then(debug::messenger_info)
+        .into_iter();
+
+    // Safe as the messenger's callback makes no Vulkan calls
+    unsafe { Instance::with_debug_utils_messengers(create_info, messengers) }.map_err(|error| {
+        InitError::UnableToCreateInstance {
+            extensions: error::extension_names(&extensions),
+            error,
+        }
+    })
+}
+
+pub fn construct_window<T>(
+    event_loop: &EventLoop<T>,
     instance: Arc<Instance>,
-) -> Result<Arc<Surface<Window>>, CreationError> {
+    (width, height): (u32, u32),
+) -> Result<Arc<Surface<Window>>, InitError> {
     WindowBuilder::new()
         .with_title("World Viewer")
-        .with_inner_size(LogicalSize::new(1280, 720))
+        .with_inner_size(LogicalSize::new(width, height))
         .build_vk_surface(event_loop, instance)
+        .map_err(InitError::UnableToCreateWindow)
+}
+
+/// An invisible window, giving a surface to check device support against.
+pub fn construct_probe_window<T>(
+    event_loop: &EventLoop<T>,
+    instance: Arc<Instance>,
+) -> Result<Arc<Surface<Window>>, InitError> {
+    WindowBuilder::new()
+        .with_title("World Viewer")
+        .with_visible(false)
+        .build_vk_surface(event_loop, instance)
+        .map_err(InitError::UnableToCreateWindow)
 }
 
 pub fn init_core_objects(
     instance: &Arc<Instance>,
     surface: &Arc<Surface<Window>>,
+    settings: &RendererSettings,
 ) -> Result<CoreObjects, InitError> {
-    let device_extensions = DeviceExtensions {
-        khr_swapchain: true,
-        ..DeviceExtensions::none()
-    };
+    let (physical_device, queue_family) = device::select(
+        device::candidates(instance, surface),
+        settings.device.as_ref(),
+    )?;
+
+    info!(
+        name = %physical_device.properties().device_name,
+        kind = ?physical_device.properties().device_type,
+        api_version = %physical_device.api_version(),
+        "Selected device"
+    );
+
+    let extensions = device::required_extensions();
+    let transfer_family = device::transfer_family(physical_device);
+    let queue_create_infos = [Some(queue_family), transfer_family]
+        .into_iter()
+        .flatten()
+        .map(QueueCreateInfo::family)
+        .collect();
 
-    let (physical_device, queue_family) = PhysicalDevice::enumerate(instance)
-        .filter(|&device| {
-            device
-                .supported_extensions()
-                .is_superset_of(&device_extensions)
-        })
-        .filter_map(|device| {
-            device
-                .queue_families()
-                .find(|&queue| {
-                    queue.supports_graphics() && queue.supports_surface(surface).unwrap_or(false)
-                })
-                .map(|queue| (device, queue))
-        })
-        .min_by_key(|(device, _)| match device.properties().device_type {
-            PhysicalDeviceType::DiscreteGpu => 0,
-            PhysicalDeviceType::IntegratedGpu => 1,
-            PhysicalDeviceType::VirtualGpu => 2,
-            PhysicalDeviceType::Cpu => 3,
-            PhysicalDeviceType::Other => 4,
-        })
-        .ok_or(InitError::NoSuitableDeviceFound)?;
+    match transfer_family {
+        Some(family) => info!(
+            family = family.id(),
+            "Uploading on a dedicated transfer queue"
+        ),
+        None => info!("No dedicated transfer queue, uploading on the graphics queue"),
+    }
 
     let (device, mut queues) = Device::new(
         physical_device,
         DeviceCreateInfo {
-            enabled_extensions: device_extensions,
-            queue_create_infos: vec![QueueCreateInfo::family(queue_family)],
+            enabled_extensions: extensions,
+            queue_create_infos,
             ..Default::default()
         },
     )
-    .map_err(InitError::UnableToCreateDevice)?;
+    .map_err(|error| InitError::UnableToCreateDevice {
+        name: physical_device.properties().device_name.clone(),
+        extensions: error::extension_names(&extensions),
+        error,
+    })?;
 
     let queue = queues.next().unwrap();
+    let transfer_queue = queues.next().unwrap_or_else(|| queue.clone());
 
     let (swapchain, images) = {
         let surface_capabilities = physical_device
             .surface_capabilities(surface, Default::default())
             .map_err(InitError::UnableToGetSurfaceCapabilities)?;
 
-        let image_format = Some(
-            physical_device
-                .surface_formats(surface, Default::default())
-                .map_err(InitError::UnableToGetSurfaceFormats)?[0]
-                .0,
-        );
+        let choice = swapchain::choose(
+            physical_device,
+            surface,
+            &surface_capabilities,
+            settings.present_mode,
+            settings.image_count,
+        )?;
 
         Swapchain::new(
             device.clone(),
             surface.clone(),
             SwapchainCreateInfo {
-                min_image_count: surface_capabilities.min_image_count,
-                image_format,
+                min_image_count: choice.image_count,
+                image_format: Some(choice.format),
+                image_color_space: choice.colour_space,
+                present_mode: choice.present_mode,
                 image_extent: surface.window().inner_size().into(),
                 image_usage: ImageUsage::color_attachment(),
                 composite_alpha: surface_capabilities
@@ -105,6 +156,7 @@ pub fn init_core_objects(
     Ok(CoreObjects {
         device,
         queue,
+        transfer_queue,
         swapchain,
         images,
     })
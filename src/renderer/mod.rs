@@ -1,31 +1,31 @@
+use egui::TexturesDelta;
 use stateloop::app::{EventLoop, Window};
-use std::{cell::RefCell, sync::Arc, u8};
+use std::{
+    cell::{RefCell, RefMut},
+    mem,
+    sync::Arc,
+};
+use tracing::{debug, debug_span, trace_span};
 use vulkano::{
-    buffer::{
-        immutable::ImmutableBufferCreationError, BufferUsage, CpuBufferPool, ImmutableBuffer,
-    },
-    device::{physical::SurfacePropertiesError, Device, DeviceCreationError, Queue},
+    buffer::{BufferAccess, BufferUsage, CpuBufferPool, ImmutableBuffer},
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
+    device::{Device, Queue},
     format::Format,
     image::{
-        view::ImageView, ImageAccess, ImageDimensions, ImageViewAbstract, ImmutableImage,
-        MipmapsCount, SwapchainImage,
+        view::ImageView, ImageAccess, ImageDimensions, ImmutableImage, MipmapsCount, SwapchainImage,
     },
-    instance::Instance,
+    instance::{debug::DebugUtilsMessenger, Instance},
     pipeline::{
         graphics::{
-            color_blend::ColorBlendState,
+            color_blend::{AttachmentBlend, BlendFactor, BlendOp, ColorBlendState},
             input_assembly::{InputAssemblyState, PrimitiveTopology},
             vertex_input::BuffersDefinition,
             viewport::{Viewport, ViewportState},
-            GraphicsPipelineCreationError,
         },
-        GraphicsPipeline,
-    },
-    render_pass::{
-        Framebuffer, FramebufferCreateInfo, RenderPass, RenderPassCreationError, Subpass,
+        GraphicsPipeline, Pipeline,
     },
+    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
     sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo},
-    shader::ShaderCreationError,
     single_pass_renderpass,
     swapchain::{
         acquire_next_image, AcquireError, Surface, Swapchain, SwapchainCreateInfo,
@@ -33,22 +33,46 @@ use vulkano::{
     },
     sync::{now, FlushError, GpuFuture},
 };
-use vulkano_win::CreationError;
+
+use crate::world::WorldConfig;
+
+pub use self::{
+    device::{DeviceDescription, DeviceSelector, Rejection},
+    error::{InitError, RenderError},
+    gui::GuiBatch,
+    primitive::PrimitiveBatch,
+    staging::{StagingBuffer, StagingPool},
+    swapchain::PresentMode,
+    text::TextBatch,
+    upload::PendingTexture,
+};
 
 use self::{
     frame::{frame_state, RenderFrame},
-    shaders::{SceneData, VertexConstants},
-    vertex::Vertex,
+    gui::GuiTextures,
+    shaders::SceneData,
+    vertex::{OverlayVertex, PrimitiveVertex, Vertex},
 };
 
+mod debug;
+mod device;
+mod error;
 mod frame;
+mod gui;
 mod init;
+mod primitive;
 mod shaders;
+mod staging;
+mod swapchain;
+mod text;
+mod upload;
 mod vertex;
 
 pub struct CoreObjects {
     device: Arc<Device>,
     queue: Arc<Queue>,
+    /// A dedicated transfer queue if the device has one, and otherwise the graphics queue.
+    transfer_queue: Arc<Queue>,
     swapchain: Arc<Swapchain<Window>>,
     images: Vec<Arc<SwapchainImage<Window>>>,
 }
@@ -64,41 +88,210 @@ pub struct RendererData {
 
     sampler: Arc<Sampler>,
 
+    overlay_vertex_buffer: CpuBufferPool<OverlayVertex>,
+    overlay_pipeline: Arc<GraphicsPipeline>,
+    glyph_atlas: Arc<PersistentDescriptorSet>,
+
+    primitive_vertex_buffer: CpuBufferPool<PrimitiveVertex>,
+    primitive_pipeline: Arc<GraphicsPipeline>,
+
+    gui_index_buffer: CpuBufferPool<u32>,
+    gui_pipeline: Arc<GraphicsPipeline>,
+    gui_textures: GuiTextures,
+
     viewport: Viewport,
     frame_future: Option<Box<dyn GpuFuture>>,
     recreate_swapchain: bool,
 }
 
 pub struct Renderer {
-    data: RefCell<RendererData>,
+    instance: Arc<Instance>,
+    _messenger: Option<DebugUtilsMessenger>,
+    config: WorldConfig,
+    settings: RendererSettings,
+    staging: Arc<StagingPool>,
+    /// Only missing if rebuilding the renderer failed, which ends the session.
+    data: RefCell<Option<RendererData>>,
 }
 
-#[derive(Debug)]
-pub enum InitError {
-    NoSuitableDeviceFound,
-    UnableToCreateDevice(DeviceCreationError),
-    UnableToGetSurfaceCapabilities(SurfacePropertiesError),
-    UnableToGetSurfaceFormats(SurfacePropertiesError),
-    UnableToCreateSwapchain(SwapchainCreationError),
-    UnableToCreateRenderPass(RenderPassCreationError),
-    UnableToCreatePipeline(GraphicsPipelineCreationError),
-    UnableToLoadShaders(ShaderCreationError),
-    UnableToCreateVertexBuffer(ImmutableBufferCreationError),
+/// User choices about how rendering is set up.
+#[derive(Debug, Clone, Default)]
+pub struct RendererSettings {
+    pub device: Option<DeviceSelector>,
+    pub present_mode: PresentMode,
+    pub image_count: Option<u32>,
+    /// Whether the instance was created for debugging, with validation and messages.
+    pub debug: bool,
 }
 
 impl Renderer {
-    pub fn construct_window(
-        event_loop: &EventLoop<()>,
+    /// Loads Vulkan, with the instance extensions needed to draw to a window. Debugging
+    /// adds the validation layer and the debug utils extension, when they are available.
+    pub fn create_instance(debug: bool) -> Result<Arc<Instance>, InitError> {
+        init::create_instance(debug)
+    }
+
+    pub fn construct_window<T>(
+        event_loop: &EventLoop<T>,
+        instance: Arc<Instance>,
+        size: (u32, u32),
+    ) -> Result<Arc<Surface<Window>>, InitError> {
+        init::construct_window(event_loop, instance, size)
+    }
+
+    pub fn construct_probe_window<T>(
+        event_loop: &EventLoop<T>,
         instance: Arc<Instance>,
-    ) -> Result<Arc<Surface<Window>>, CreationError> {
-        init::construct_window(event_loop, instance)
+    ) -> Result<Arc<Surface<Window>>, InitError> {
+        init::construct_probe_window(event_loop, instance)
+    }
+
+    /// Every physical device, and whether it could render to the given surface.
+    pub fn describe_devices(
+        instance: &Arc<Instance>,
+        surface: &Arc<Surface<Window>>,
+    ) -> Vec<DeviceDescription> {
+        device::describe(&device::candidates(instance, surface))
     }
 
     pub fn init_vulkan(
         instance: &Arc<Instance>,
         surface: &Arc<Surface<Window>>,
+        config: &WorldConfig,
+        settings: &RendererSettings,
+    ) -> Result<Self, InitError> {
+        // Before the device, so that messages from creating it are logged
+        let messenger = debug::messenger(instance);
+        let data = RendererData::new(instance, surface, config, settings)?;
+
+        Ok(Self {
+            instance: instance.clone(),
+            _messenger: messenger,
+            config: *config,
+            settings: settings.clone(),
+            staging: Arc::new(StagingPool::new(data.objects.device.clone())),
+            data: RefCell::new(Some(data)),
+        })
+    }
+
+    /// Replaces everything created with the device, after the device has been lost.
+    /// Textures from before are no longer usable and must be created again.
+    pub fn recover(&self, surface: &Arc<Surface<Window>>) -> Result<(), InitError> {
+        // The window can only have one swapchain, so the old one has to go first
+        let gui_textures = match self.data.take() {
+            Some(mut data) => {
+                data.abandon();
+                mem::take(&mut data.gui_textures).lost()
+            }
+            None => GuiTextures::default(),
+        };
+
+        let mut data = RendererData::new(&self.instance, surface, &self.config, &self.settings)?;
+        data.gui_textures = gui_textures;
+        self.staging.set_device(data.objects.device.clone());
+        self.data.replace(Some(data));
+        Ok(())
+    }
+
+    /// Applies changes to the interface's textures, which are uploaded when next drawn.
+    pub fn update_gui_textures(&self, delta: TexturesDelta) {
+        self.data().gui_textures.update(delta);
+    }
+
+    /// Recreates the swapchain before the next frame, to match the window's new size.
+    pub fn resized(&self) {
+        self.data().recreate_swapchain = true;
+    }
+
+    /// Buffers for textures to be written into away from the main thread, ready to be
+    /// passed to `create_texture`.
+    pub fn staging_pool(&self) -> Arc<StagingPool> {
+        self.staging.clone()
+    }
+
+    /// Starts uploading an image to sample from, named `name` when debugging. The upload
+    /// goes through the transfer queue, and the texture is ready once it has finished.
+    pub fn create_texture(
+        &self,
+        name: &str,
+        texels: StagingBuffer,
+        width: u32,
+        height: u32,
+        format: Format,
+    ) -> Result<PendingTexture, RenderError> {
+        let _span = debug_span!("create_texture", name, width, height).entered();
+        let data = self.data();
+
+        let source = texels.into_source(&data.objects.device)?;
+        debug::name(&**source.inner().buffer, &format!("{} staging", name));
+
+        let (image, upload) = ImmutableImage::from_buffer(
+            source,
+            ImageDimensions::Dim2d {
+                width,
+                height,
+                array_layers: 1,
+            },
+            MipmapsCount::One,
+            format,
+            data.objects.transfer_queue.clone(),
+        )?;
+
+        debug::name(&**image.inner().image, name);
+        let texture = ImageView::new_default(image)?;
+
+        Ok(PendingTexture::new(
+            texture,
+            upload.then_signal_fence_and_flush()?,
+        ))
+    }
+
+    pub fn render<F>(
+        &self,
+        surface: &Arc<Surface<Window>>,
+        frame_callback: F,
+    ) -> Result<(), RenderError>
+    where
+        F: FnOnce(RenderFrame<frame_state::RenderPass>) -> RenderFrame<frame_state::Done>,
+    {
+        let _span = trace_span!("render").entered();
+        let mut data = self.data();
+        let result = data.render(surface, frame_callback);
+
+        if let Err(RenderError::DeviceLost) = result {
+            data.abandon();
+        }
+
+        result
+    }
+
+    fn data(&self) -> RefMut<'_, RendererData> {
+        RefMut::map(self.data.borrow_mut(), |data| {
+            data.as_mut()
+                .expect("renderer used after failing to rebuild")
+        })
+    }
+}
+
+impl RendererData {
+    fn new(
+        instance: &Arc<Instance>,
+        surface: &Arc<Surface<Window>>,
+        config: &WorldConfig,
+        settings: &RendererSettings,
     ) -> Result<Self, InitError> {
-        let objects = init::init_core_objects(instance, surface)?;
+        let objects = init::init_core_objects(instance, surface, settings)?;
+
+        let properties = objects.device.physical_device().properties();
+        let max_size = properties.max_image_dimension2_d;
+
+        if config.chunk_size() > max_size {
+            return Err(InitError::ChunkTooLarge {
+                name: properties.device_name.clone(),
+                chunk_size: config.chunk_size(),
+                max_size,
+            });
+        }
 
         let render_pass = single_pass_renderpass!(
             objects.device.clone(),
@@ -156,173 +349,282 @@ impl Renderer {
 
         let pipeline = GraphicsPipeline::start()
             .vertex_input_state(BuffersDefinition::new().vertex::<Vertex>())
-            .vertex_shader(
-                shaders.vertex.entry_point("main").unwrap(),
-                VertexConstants { quad_scale: 300.0 },
-            )
+            .vertex_shader(shaders.vertex.entry_point("main").unwrap(), ())
             .input_assembly_state(
                 InputAssemblyState::new().topology(PrimitiveTopology::TriangleStrip),
             )
             .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
             .fragment_shader(shaders.fragment.entry_point("main").unwrap(), ())
             .color_blend_state(ColorBlendState::new(subpass.num_color_attachments()).blend_alpha())
+            .render_pass(subpass.clone())
+            .build(objects.device.clone())
+            .map_err(InitError::UnableToCreatePipeline)?;
+
+        let overlay_pipeline = GraphicsPipeline::start()
+            .vertex_input_state(BuffersDefinition::new().vertex::<OverlayVertex>())
+            .vertex_shader(shaders.overlay_vertex.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(shaders.overlay_fragment.entry_point("main").unwrap(), ())
+            .color_blend_state(ColorBlendState::new(subpass.num_color_attachments()).blend_alpha())
+            .render_pass(subpass.clone())
+            .build(objects.device.clone())
+            .map_err(InitError::UnableToCreatePipeline)?;
+
+        let primitive_pipeline = GraphicsPipeline::start()
+            .vertex_input_state(BuffersDefinition::new().vertex::<PrimitiveVertex>())
+            .vertex_shader(shaders.primitive_vertex.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(shaders.primitive_fragment.entry_point("main").unwrap(), ())
+            .color_blend_state(ColorBlendState::new(subpass.num_color_attachments()).blend_alpha())
+            .render_pass(subpass.clone())
+            .build(objects.device.clone())
+            .map_err(InitError::UnableToCreatePipeline)?;
+
+        // egui draws indexed triangles in premultiplied colours, clipped per mesh
+        let gui_pipeline = GraphicsPipeline::start()
+            .vertex_input_state(BuffersDefinition::new().vertex::<OverlayVertex>())
+            .vertex_shader(shaders.overlay_vertex.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_dynamic(1))
+            .fragment_shader(shaders.gui_fragment.entry_point("main").unwrap(), ())
+            .color_blend_state(ColorBlendState::new(subpass.num_color_attachments()).blend(
+                AttachmentBlend {
+                    color_op: BlendOp::Add,
+                    color_source: BlendFactor::One,
+                    color_destination: BlendFactor::OneMinusSrcAlpha,
+                    alpha_op: BlendOp::Add,
+                    alpha_source: BlendFactor::One,
+                    alpha_destination: BlendFactor::OneMinusSrcAlpha,
+                },
+            ))
             .render_pass(subpass)
             .build(objects.device.clone())
             .map_err(InitError::UnableToCreatePipeline)?;
 
-        Ok(Self {
-            data: RefCell::new(RendererData {
-                objects,
+        let (atlas, atlas_future) = ImmutableImage::from_iter(
+            text::atlas(),
+            ImageDimensions::Dim2d {
+                width: text::ATLAS_WIDTH,
+                height: text::ATLAS_HEIGHT,
+                array_layers: 1,
+            },
+            MipmapsCount::One,
+            Format::R8_UNORM,
+            objects.queue.clone(),
+        )
+        .map_err(InitError::UnableToCreateGlyphAtlas)?;
 
-                vertex_buffer,
-                uniform_buffer,
-                pipeline,
-                render_pass,
-                framebuffers: None,
+        debug::name(&*pipeline, "chunk pipeline");
+        debug::name(&*overlay_pipeline, "text pipeline");
+        debug::name(&*primitive_pipeline, "primitive pipeline");
+        debug::name(&*gui_pipeline, "gui pipeline");
+        debug::name(&**vertex_buffer.inner().buffer, "chunk vertex buffer");
+        debug::name(&**atlas.inner().image, "glyph atlas");
 
-                sampler,
+        let atlas_sampler = Sampler::new(
+            objects.device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Nearest,
+                min_filter: Filter::Nearest,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )
+        .unwrap();
 
-                viewport,
-                frame_future: Some(Box::new(buffer_future)),
-                recreate_swapchain: false,
-            }),
-        })
-    }
+        let glyph_atlas = PersistentDescriptorSet::new(
+            overlay_pipeline.layout().set_layouts()[1].clone(),
+            [WriteDescriptorSet::image_view_sampler(
+                0,
+                ImageView::new_default(atlas).unwrap(),
+                atlas_sampler,
+            )],
+        )
+        .unwrap();
 
-    pub fn create_texture<I>(
-        &self,
-        input: I,
-        width: u32,
-        height: u32,
-        format: Format,
-    ) -> Arc<dyn ImageViewAbstract>
-    where
-        I: IntoIterator<Item = u8>,
-        I::IntoIter: ExactSizeIterator,
-    {
-        let mut data = self.data.borrow_mut();
+        let overlay_vertex_buffer =
+            CpuBufferPool::<OverlayVertex>::vertex_buffer(objects.device.clone());
+        let primitive_vertex_buffer =
+            CpuBufferPool::<PrimitiveVertex>::vertex_buffer(objects.device.clone());
+        let gui_index_buffer =
+            CpuBufferPool::<u32>::new(objects.device.clone(), BufferUsage::index_buffer());
 
-        let frame_future = data.frame_future.take().unwrap();
+        Ok(RendererData {
+            objects,
 
-        let (texture, texture_future) = {
-            let (image, future) = ImmutableImage::from_iter(
-                input,
-                ImageDimensions::Dim2d {
-                    width,
-                    height,
-                    array_layers: 1,
-                },
-                MipmapsCount::One,
-                format,
-                data.objects.queue.clone(),
-            )
-            .unwrap();
+            vertex_buffer,
+            uniform_buffer,
+            pipeline,
+            render_pass,
+            framebuffers: None,
 
-            (ImageView::new_default(image).unwrap(), future)
-        };
+            sampler,
+
+            overlay_vertex_buffer,
+            overlay_pipeline,
+            glyph_atlas,
+
+            primitive_vertex_buffer,
+            primitive_pipeline,
 
-        let frame_future = frame_future.join(texture_future);
-        data.frame_future = Some(Box::new(frame_future));
+            gui_index_buffer,
+            gui_pipeline,
+            gui_textures: GuiTextures::default(),
+
+            viewport,
+            frame_future: Some(Box::new(buffer_future.join(atlas_future))),
+            recreate_swapchain: false,
+        })
+    }
+
+    /// Takes the future of the work submitted so far, to chain more work onto.
+    fn take_future(&mut self) -> Box<dyn GpuFuture> {
+        self.frame_future
+            .take()
+            .unwrap_or_else(|| Box::new(now(self.objects.device.clone())))
+    }
+
+    /// Gives up on the work submitted so far after the device has been lost. Its fences
+    /// and semaphores will never be signalled, and dropping it would wait on them and panic.
+    fn abandon(&mut self) {
+        if let Some(future) = self.frame_future.take() {
+            mem::forget(future);
+        }
+    }
 
-        texture
+    /// Makes the next frame wait for `future` as well as the work before it.
+    fn chain<F: GpuFuture + 'static>(&mut self, future: F) {
+        let previous = self.take_future();
+        self.frame_future = Some(Box::new(previous.join(future)));
     }
 
-    pub fn render<F>(&self, surface: &Arc<Surface<Window>>, frame_callback: F)
+    fn render<F>(
+        &mut self,
+        surface: &Arc<Surface<Window>>,
+        frame_callback: F,
+    ) -> Result<(), RenderError>
     where
         F: FnOnce(RenderFrame<frame_state::RenderPass>) -> RenderFrame<frame_state::Done>,
     {
-        let mut data = self.data.borrow_mut();
-
-        let mut frame_future = data.frame_future.take().unwrap();
-        frame_future.cleanup_finished();
+        if let Some(frame_future) = &mut self.frame_future {
+            frame_future.cleanup_finished();
+        }
 
         let dimensions = surface.window().inner_size();
 
-        if data.recreate_swapchain {
-            let (swapchain, images) = match data.objects.swapchain.recreate(SwapchainCreateInfo {
-                image_extent: dimensions.into(),
-                ..data.objects.swapchain.create_info()
+        // A minimised window has nothing to present to until it is restored
+        if dimensions.width == 0 || dimensions.height == 0 {
+            return Ok(());
+        }
+
+        if self.recreate_swapchain {
+            let _span = debug_span!("recreate_swapchain").entered();
+
+            // While the window is being dragged the surface may already have moved on from
+            // the size the window last reported, and it is the surface's size that counts
+            let image_extent = self
+                .objects
+                .device
+                .physical_device()
+                .surface_capabilities(surface, Default::default())?
+                .current_extent
+                .unwrap_or_else(|| dimensions.into());
+
+            if image_extent.contains(&0) {
+                return Ok(());
+            }
+
+            let (swapchain, images) = match self.objects.swapchain.recreate(SwapchainCreateInfo {
+                image_extent,
+                ..self.objects.swapchain.create_info()
             }) {
                 Ok(result) => result,
-                Err(SwapchainCreationError::ImageExtentNotSupported { .. }) => return,
-                Err(e) => panic!("{:?}", e),
+                Err(SwapchainCreationError::ImageExtentNotSupported { .. }) => return Ok(()),
+                Err(e) => return Err(e.into()),
             };
 
-            data.objects.swapchain = swapchain;
-            data.objects.images = images;
-            data.framebuffers = None;
-            data.recreate_swapchain = false;
+            self.objects.swapchain = swapchain;
+            self.objects.images = images;
+            self.framebuffers = None;
+            self.recreate_swapchain = false;
+
+            debug!(
+                width = image_extent[0],
+                height = image_extent[1],
+                "Recreated swapchain"
+            );
         }
 
-        if data.framebuffers.is_none() {
-            let [w, h] = data.objects.images[0].dimensions().width_height();
-            data.viewport.dimensions = [w as f32, h as f32];
+        if self.framebuffers.is_none() {
+            let [w, h] = self.objects.images[0].dimensions().width_height();
+            self.viewport.dimensions = [w as f32, h as f32];
 
-            let framebuffers = data
+            let framebuffers = self
                 .objects
                 .images
                 .iter()
                 .map(|image| {
-                    let view = ImageView::new_default(image.clone()).unwrap();
+                    let view = ImageView::new_default(image.clone())?;
 
-                    Framebuffer::new(
-                        data.render_pass.clone(),
+                    Ok(Framebuffer::new(
+                        self.render_pass.clone(),
                         FramebufferCreateInfo {
                             attachments: vec![view],
                             ..Default::default()
                         },
-                    )
-                    .unwrap()
+                    )?)
                 })
-                .collect::<Vec<_>>();
+                .collect::<Result<Vec<_>, RenderError>>()?;
 
-            data.framebuffers = Some(framebuffers);
+            self.framebuffers = Some(framebuffers);
         }
 
         let (image_num, suboptimal, acquire_future) =
-            match acquire_next_image(data.objects.swapchain.clone(), None) {
+            match acquire_next_image(self.objects.swapchain.clone(), None) {
                 Ok(result) => result,
                 Err(AcquireError::OutOfDate) => {
-                    data.recreate_swapchain = true;
-                    return;
+                    self.recreate_swapchain = true;
+                    return Ok(());
                 }
-                Err(e) => panic!("{:?}", e),
+                Err(e) => return Err(e.into()),
             };
 
         if suboptimal {
-            data.recreate_swapchain = true;
+            self.recreate_swapchain = true;
         }
 
-        let frame = RenderFrame::new(&mut data, image_num).begin(
+        let frame = RenderFrame::new(self, image_num)?.begin(
             surface
                 .window()
                 .inner_size()
                 .to_logical::<f32>(surface.window().scale_factor()),
         );
 
-        let builder = frame_callback(frame).unwrap();
-        let command_buffer = builder.build().unwrap();
+        let command_buffer = frame_callback(frame).into_builder()?.build()?;
 
-        let future = frame_future
+        let future = self
+            .take_future()
             .join(acquire_future)
-            .then_execute(data.objects.queue.clone(), command_buffer)
-            .unwrap()
+            .then_execute(self.objects.queue.clone(), command_buffer)?
             .then_swapchain_present(
-                data.objects.queue.clone(),
-                data.objects.swapchain.clone(),
+                self.objects.queue.clone(),
+                self.objects.swapchain.clone(),
                 image_num,
             )
             .then_signal_fence_and_flush();
 
-        let end_future = match future {
-            Ok(future) => Box::new(future) as Box<_>,
+        match future {
+            Ok(future) => {
+                self.frame_future = Some(Box::new(future));
+                Ok(())
+            }
             Err(FlushError::OutOfDate) => {
-                data.recreate_swapchain = true;
-                Box::new(now(data.objects.device.clone())) as Box<_>
+                self.recreate_swapchain = true;
+                Ok(())
             }
-            Err(_) => Box::new(now(data.objects.device.clone())) as Box<_>,
-        };
-
-        data.frame_future = Some(end_future);
+            Err(e) => Err(e.into()),
+        }
     }
 }
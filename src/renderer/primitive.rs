@@ -0,0 +1,84 @@
+use super::vertex::PrimitiveVertex;
+
+/// Untextured screen space shapes, positioned in logical pixels.
+#[derive(Default)]
+pub struct PrimitiveBatch {
+    vertices: Vec<PrimitiveVertex>,
+}
+
+impl PrimitiveBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vertices(&self) -> &[PrimitiveVertex] {
+        &self.vertices
+    }
+
+    pub fn rect(&mut self, position: [f32; 2], size: [f32; 2], colour: [f32; 4]) {
+        let corner = |x: f32, y: f32| [position[0] + x * size[0], position[1] + y * size[1]];
+
+        self.quad(
+            [
+                corner(0.0, 0.0),
+                corner(1.0, 0.0),
+                corner(0.0, 1.0),
+                corner(1.0, 1.0),
+            ],
+            colour,
+        );
+    }
+
+    /// A line of the given width, centred on the segment between `from` and `to`.
+    pub fn line(&mut self, from: [f32; 2], to: [f32; 2], width: f32, colour: [f32; 4]) {
+        let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+        let length = (dx * dx + dy * dy).sqrt();
+
+        if length == 0.0 {
+            return;
+        }
+
+        let normal = [-dy / length * width / 2.0, dx / length * width / 2.0];
+
+        self.quad(
+            [
+                [from[0] + normal[0], from[1] + normal[1]],
+                [to[0] + normal[0], to[1] + normal[1]],
+                [from[0] - normal[0], from[1] - normal[1]],
+                [to[0] - normal[0], to[1] - normal[1]],
+            ],
+            colour,
+        );
+    }
+
+    /// The border of a rectangle, drawn inside its bounds.
+    pub fn outline(&mut self, position: [f32; 2], size: [f32; 2], width: f32, colour: [f32; 4]) {
+        let inset = width / 2.0;
+        let (left, top) = (position[0] + inset, position[1] + inset);
+        let (right, bottom) = (position[0] + size[0] - inset, position[1] + size[1] - inset);
+
+        // The horizontal edges cover the corners, so the vertical edges stop short of them
+        self.line([left - inset, top], [right + inset, top], width, colour);
+        self.line(
+            [left - inset, bottom],
+            [right + inset, bottom],
+            width,
+            colour,
+        );
+        self.line([left, top + inset], [left, bottom - inset], width, colour);
+        self.line([right, top + inset], [right, bottom - inset], width, colour);
+    }
+
+    fn quad(&mut self, corners: [[f32; 2]; 4], colour: [f32; 4]) {
+        let vertex = |index: usize| PrimitiveVertex::new(corners[index], colour);
+
+        self.vertices.extend_from_slice(&[
+            vertex(0),
+            vertex(1),
+            vertex(2),
+            vertex(2),
+            vertex(1),
+            vertex(3),
+        ]);
+    }
+}
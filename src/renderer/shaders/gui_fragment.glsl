@@ -0,0 +1,12 @@
+#version 450
+
+layout(location = 0) in vec2 uv;
+layout(location = 1) in vec4 tint;
+
+layout(set = 1, binding = 0) uniform sampler2D image;
+
+layout(location = 0) out vec4 colour;
+
+void main() {
+    colour = tint * texture(image, uv);
+}
\ No newline at end of file
@@ -6,6 +6,7 @@ use vulkano::{
     shader::{ShaderCreationError, ShaderModule},
 };
 
+#[allow(clippy::needless_question_mark)]
 mod vs {
     vulkano_shaders::shader! {
         ty: "vertex",
@@ -18,6 +19,7 @@ mod vs {
     }
 }
 
+#[allow(clippy::needless_question_mark)]
 mod fs {
     vulkano_shaders::shader! {
         ty: "fragment",
@@ -25,19 +27,68 @@ mod fs {
     }
 }
 
+#[allow(clippy::needless_question_mark)]
+mod overlay_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/renderer/shaders/overlay_vertex.glsl",
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod overlay_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/renderer/shaders/overlay_fragment.glsl",
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod primitive_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/renderer/shaders/primitive_vertex.glsl",
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod primitive_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/renderer/shaders/primitive_fragment.glsl",
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod gui_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/renderer/shaders/gui_fragment.glsl",
+    }
+}
+
 pub struct Shaders {
     pub vertex: Arc<ShaderModule>,
     pub fragment: Arc<ShaderModule>,
+    pub overlay_vertex: Arc<ShaderModule>,
+    pub overlay_fragment: Arc<ShaderModule>,
+    pub primitive_vertex: Arc<ShaderModule>,
+    pub primitive_fragment: Arc<ShaderModule>,
+    pub gui_fragment: Arc<ShaderModule>,
 }
 
 pub use vs::ty::MeshData;
 pub use vs::ty::SceneData;
-pub use vs::SpecializationConstants as VertexConstants;
 
 pub fn load(device: Arc<Device>) -> Result<Shaders, ShaderCreationError> {
     Ok(Shaders {
         vertex: vs::load(device.clone())?,
         fragment: fs::load(device.clone())?,
+        overlay_vertex: overlay_vs::load(device.clone())?,
+        overlay_fragment: overlay_fs::load(device.clone())?,
+        primitive_vertex: primitive_vs::load(device.clone())?,
+        primitive_fragment: primitive_fs::load(device.clone())?,
+        gui_fragment: gui_fs::load(device)?,
     })
 }
 
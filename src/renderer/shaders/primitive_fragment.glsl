@@ -0,0 +1,9 @@
+#version 450
+
+layout(location = 0) in vec4 tint;
+
+layout(location = 0) out vec4 colour;
+
+void main() {
+    colour = tint;
+}
\ No newline at end of file
@@ -9,14 +9,13 @@ layout(set = 0, binding = 0) uniform SceneData {
 
 layout(push_constant) uniform MeshData {
     vec2 offset;
+    float size;
 } mesh;
 
-layout(constant_id = 0) const float quad_scale = 100;
-
 layout(location = 0) out vec2 uv;
 
 void main() {
-    vec2 scaled = position * quad_scale;
+    vec2 scaled = position * mesh.size;
     vec2 offset = scaled + mesh.offset;
     vec2 adjusted = 2.0 * offset / scene.size - 1.0;
 
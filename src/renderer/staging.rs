@@ -0,0 +1,128 @@
+use std::{
+    iter,
+    sync::{Arc, Mutex},
+};
+
+use tracing::warn;
+use vulkano::{
+    buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer, TypedBufferAccess},
+    device::{Device, DeviceOwned},
+};
+
+use super::RenderError;
+use crate::world::TexelPool;
+
+/// How many unused buffers are kept around for the next chunks.
+const MAX_IDLE_BUFFERS: usize = 32;
+
+/// Host-visible buffers for the generation threads to write textures into, which the
+/// GPU then copies from directly. A buffer is reused once nothing else holds it, which
+/// includes the upload that reads from it.
+pub struct StagingPool {
+    device: Mutex<Arc<Device>>,
+    buffers: Mutex<Vec<Arc<CpuAccessibleBuffer<[u8]>>>>,
+}
+
+/// Texels written by a generation thread, on the heap if no staging buffer could be had.
+pub enum StagingBuffer {
+    Device(Arc<CpuAccessibleBuffer<[u8]>>),
+    Host(Vec<u8>),
+}
+
+impl StagingPool {
+    pub fn new(device: Arc<Device>) -> Self {
+        Self {
+            device: Mutex::new(device),
+            buffers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Allocates from `device` from now on, once the previous device has been lost.
+    pub fn set_device(&self, device: Arc<Device>) {
+        *self.device.lock().unwrap() = device;
+        self.buffers.lock().unwrap().clear();
+    }
+
+    fn take(&self, len: usize) -> Option<Arc<CpuAccessibleBuffer<[u8]>>> {
+        let device = self.device.lock().unwrap().clone();
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let idle = |buffer: &Arc<CpuAccessibleBuffer<[u8]>>| Arc::strong_count(buffer) == 1;
+
+        // Anything allocated while the device was being replaced is of no further use
+        buffers.retain(|buffer| Arc::ptr_eq(buffer.device(), &device));
+
+        if let Some(buffer) = buffers
+            .iter()
+            .find(|&buffer| idle(buffer) && buffer.len() == len as u64)
+        {
+            return Some(buffer.clone());
+        }
+
+        if buffers.iter().filter(|&buffer| idle(buffer)).count() >= MAX_IDLE_BUFFERS {
+            let position = buffers.iter().position(idle).unwrap();
+            buffers.swap_remove(position);
+        }
+
+        // Other threads can reuse buffers while this one is allocated
+        drop(buffers);
+
+        match CpuAccessibleBuffer::from_iter(device, transfer_src(), false, iter::repeat_n(0, len))
+        {
+            Ok(buffer) => {
+                self.buffers.lock().unwrap().push(buffer.clone());
+                Some(buffer)
+            }
+            Err(e) => {
+                warn!("Unable to allocate staging buffer: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl TexelPool for StagingPool {
+    type Buffer = StagingBuffer;
+
+    fn fill<F: FnOnce(&mut [u8])>(&self, len: usize, fill: F) -> StagingBuffer {
+        if let Some(buffer) = self.take(len) {
+            // Only fails if the buffer is still in use, which being idle rules out
+            if let Ok(mut texels) = buffer.write() {
+                fill(&mut texels);
+                return StagingBuffer::Device(buffer.clone());
+            }
+        }
+
+        let mut texels = vec![0; len];
+        fill(&mut texels);
+        StagingBuffer::Host(texels)
+    }
+}
+
+impl StagingBuffer {
+    /// A buffer on `device` for the GPU to copy the texels from. Only texels that missed
+    /// out on a staging buffer, or were written before the device was lost, are copied.
+    pub fn into_source(self, device: &Arc<Device>) -> Result<Arc<dyn BufferAccess>, RenderError> {
+        let copy = |texels: &[u8]| {
+            CpuAccessibleBuffer::from_iter(
+                device.clone(),
+                transfer_src(),
+                false,
+                texels.iter().copied(),
+            )
+        };
+
+        Ok(match self {
+            StagingBuffer::Device(buffer) if Arc::ptr_eq(buffer.device(), device) => buffer,
+            StagingBuffer::Device(buffer) => copy(&buffer.read()?)?,
+            StagingBuffer::Host(texels) => copy(&texels)?,
+        })
+    }
+}
+
+fn transfer_src() -> BufferUsage {
+    BufferUsage {
+        transfer_src: true,
+        ..BufferUsage::none()
+    }
+}
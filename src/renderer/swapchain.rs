@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use stateloop::app::Window;
+use tracing::{info, warn};
+use vulkano::{
+    device::physical::PhysicalDevice,
+    format::{Format, NumericType},
+    swapchain::{self, ColorSpace, Surface, SurfaceCapabilities},
+};
+
+use super::InitError;
+
+/// The chunk textures are sRGB, so the surface should be too for colours to come out
+/// unchanged.
+const PREFERRED_FORMAT: Format = Format::R8G8B8A8_SRGB;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PresentMode {
+    /// Wait for vertical blank, always supported
+    #[default]
+    Fifo,
+    /// Wait for vertical blank, replacing queued frames with newer ones
+    Mailbox,
+    /// Present immediately, which may tear
+    Immediate,
+}
+
+/// How the swapchain is set up, after checking the requested settings against what the
+/// surface supports.
+pub struct SwapchainChoice {
+    pub present_mode: swapchain::PresentMode,
+    pub image_count: u32,
+    pub format: Format,
+    pub colour_space: ColorSpace,
+}
+
+impl From<PresentMode> for swapchain::PresentMode {
+    fn from(mode: PresentMode) -> Self {
+        match mode {
+            PresentMode::Fifo => swapchain::PresentMode::Fifo,
+            PresentMode::Mailbox => swapchain::PresentMode::Mailbox,
+            PresentMode::Immediate => swapchain::PresentMode::Immediate,
+        }
+    }
+}
+
+/// Chooses the present mode, falling back to FIFO, an image count within the surface's
+/// limits, defaulting to one more than the minimum, and a format, preferring sRGB. Each
+/// choice is reported as it is made.
+pub fn choose(
+    device: PhysicalDevice,
+    surface: &Arc<Surface<Window>>,
+    capabilities: &SurfaceCapabilities,
+    present_mode: PresentMode,
+    image_count: Option<u32>,
+) -> Result<SwapchainChoice, InitError> {
+    let requested = present_mode.into();
+    let supported = device
+        .surface_present_modes(surface)
+        .map_err(InitError::UnableToGetPresentModes)?
+        .any(|mode| mode == requested);
+
+    let present_mode = if supported {
+        requested
+    } else {
+        swapchain::PresentMode::Fifo
+    };
+
+    if present_mode == requested {
+        info!(?present_mode, "Chose present mode");
+    } else {
+        warn!(
+            ?present_mode,
+            ?requested,
+            "Requested present mode is not supported"
+        );
+    }
+
+    let min = capabilities.min_image_count;
+    let max = capabilities.max_image_count.unwrap_or(u32::MAX);
+    let image_count = image_count.unwrap_or(min + 1).clamp(min, max);
+
+    info!(
+        image_count,
+        min,
+        max = capabilities.max_image_count,
+        "Chose swapchain image count"
+    );
+
+    let formats = device
+        .surface_formats(surface, Default::default())
+        .map_err(InitError::UnableToGetSurfaceFormats)?;
+
+    let srgb = |format: Format, colour_space| {
+        colour_space == ColorSpace::SrgbNonLinear && format.type_color() == Some(NumericType::SRGB)
+    };
+
+    let (format, colour_space) = formats
+        .iter()
+        .find(|&&(format, colour_space)| {
+            format == PREFERRED_FORMAT && colour_space == ColorSpace::SrgbNonLinear
+        })
+        .or_else(|| {
+            formats
+                .iter()
+                .find(|&&(format, colour_space)| srgb(format, colour_space))
+        })
+        .or_else(|| formats.first())
+        .copied()
+        .ok_or(InitError::NoSurfaceFormats)?;
+
+    if srgb(format, colour_space) {
+        info!(?format, ?colour_space, "Chose surface format");
+    } else {
+        warn!(
+            ?format,
+            ?colour_space,
+            "No sRGB surface format is available, colours may be wrong"
+        );
+    }
+
+    Ok(SwapchainChoice {
+        present_mode,
+        image_count,
+        format,
+        colour_space,
+    })
+}
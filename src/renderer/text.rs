@@ -0,0 +1,140 @@
+use font8x8::legacy::BASIC_LEGACY;
+
+use super::vertex::OverlayVertex;
+
+const GLYPH_SIZE: u32 = 8;
+const ATLAS_COLUMNS: u32 = 16;
+const ATLAS_ROWS: u32 = 8;
+
+pub const ATLAS_WIDTH: u32 = GLYPH_SIZE * ATLAS_COLUMNS;
+pub const ATLAS_HEIGHT: u32 = GLYPH_SIZE * ATLAS_ROWS;
+
+// The NUL glyph is never drawn as text, so it is filled in to give solid quads
+const SOLID_GLYPH: u8 = 0;
+const UNKNOWN_GLYPH: u8 = b'?';
+
+const PANEL_PADDING: f32 = 6.0;
+const PANEL_COLOUR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const TEXT_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// Rasterises the basic ASCII font into a single channel atlas, 16 glyphs per row.
+pub fn atlas() -> Vec<u8> {
+    let mut data = vec![0; (ATLAS_WIDTH * ATLAS_HEIGHT) as usize];
+
+    for (index, glyph) in BASIC_LEGACY.iter().enumerate() {
+        let (column, row) = (index as u32 % ATLAS_COLUMNS, index as u32 / ATLAS_COLUMNS);
+
+        for (y, bits) in glyph.iter().enumerate() {
+            for x in 0..GLYPH_SIZE {
+                let set = index == SOLID_GLYPH as usize || bits & (1 << x) != 0;
+                let offset = (row * GLYPH_SIZE + y as u32) * ATLAS_WIDTH + column * GLYPH_SIZE + x;
+                data[offset as usize] = if set { u8::MAX } else { 0 };
+            }
+        }
+    }
+
+    data
+}
+
+/// Screen space quads textured from the glyph atlas, positioned in logical pixels.
+#[derive(Default)]
+pub struct TextBatch {
+    vertices: Vec<OverlayVertex>,
+}
+
+impl TextBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vertices(&self) -> &[OverlayVertex] {
+        &self.vertices
+    }
+
+    pub fn rect(&mut self, position: [f32; 2], size: [f32; 2], colour: [f32; 4]) {
+        self.glyph(SOLID_GLYPH, position, size, colour);
+    }
+
+    pub fn text(&mut self, position: [f32; 2], scale: f32, colour: [f32; 4], text: &str) {
+        let advance = GLYPH_SIZE as f32 * scale;
+
+        for (index, c) in text.chars().enumerate() {
+            let glyph = match c {
+                ' '..='~' => c as u8,
+                _ => UNKNOWN_GLYPH,
+            };
+
+            self.glyph(
+                glyph,
+                [position[0] + index as f32 * advance, position[1]],
+                [advance, advance],
+                colour,
+            );
+        }
+    }
+
+    /// How much taller a panel is for each extra line at `scale`.
+    pub fn line_height(scale: f32) -> f32 {
+        GLYPH_SIZE as f32 * scale
+    }
+
+    /// Draws lines of text over a translucent background, with the top-left corner at `position`.
+    pub fn panel<S: AsRef<str>>(&mut self, position: [f32; 2], scale: f32, lines: &[S]) {
+        let line_height = Self::line_height(scale);
+        let columns = lines
+            .iter()
+            .map(|line| line.as_ref().chars().count())
+            .max()
+            .unwrap_or(0);
+
+        self.rect(
+            position,
+            [
+                columns as f32 * line_height + 2.0 * PANEL_PADDING,
+                lines.len() as f32 * line_height + 2.0 * PANEL_PADDING,
+            ],
+            PANEL_COLOUR,
+        );
+
+        for (index, line) in lines.iter().enumerate() {
+            self.text(
+                [
+                    position[0] + PANEL_PADDING,
+                    position[1] + PANEL_PADDING + index as f32 * line_height,
+                ],
+                scale,
+                TEXT_COLOUR,
+                line.as_ref(),
+            );
+        }
+    }
+
+    fn glyph(&mut self, glyph: u8, position: [f32; 2], size: [f32; 2], colour: [f32; 4]) {
+        let (column, row) = (glyph as u32 % ATLAS_COLUMNS, glyph as u32 / ATLAS_COLUMNS);
+        let uv = [
+            (column * GLYPH_SIZE) as f32 / ATLAS_WIDTH as f32,
+            (row * GLYPH_SIZE) as f32 / ATLAS_HEIGHT as f32,
+        ];
+        let uv_size = [
+            GLYPH_SIZE as f32 / ATLAS_WIDTH as f32,
+            GLYPH_SIZE as f32 / ATLAS_HEIGHT as f32,
+        ];
+
+        let corner = |x: f32, y: f32| {
+            OverlayVertex::new(
+                [position[0] + x * size[0], position[1] + y * size[1]],
+                [uv[0] + x * uv_size[0], uv[1] + y * uv_size[1]],
+                colour,
+            )
+        };
+
+        self.vertices.extend_from_slice(&[
+            corner(0.0, 0.0),
+            corner(1.0, 0.0),
+            corner(0.0, 1.0),
+            corner(0.0, 1.0),
+            corner(1.0, 0.0),
+            corner(1.0, 1.0),
+        ]);
+    }
+}
@@ -0,0 +1,53 @@
+use std::{mem, sync::Arc};
+
+use vulkano::{
+    command_buffer::{CommandBufferExecFuture, PrimaryAutoCommandBuffer},
+    image::ImageViewAbstract,
+    sync::{FenceSignalFuture, NowFuture},
+};
+
+use super::RenderError;
+
+type Upload = FenceSignalFuture<CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>>;
+
+/// A texture whose upload has been submitted, which cannot be drawn until it has finished.
+///
+/// Completion is seen through a fence before the texture is handed out, so a frame never
+/// has to wait on the upload's queue, and nothing on that queue waits on a frame.
+pub struct PendingTexture {
+    texture: Arc<dyn ImageViewAbstract>,
+    upload: Option<Upload>,
+}
+
+impl PendingTexture {
+    pub(super) fn new(texture: Arc<dyn ImageViewAbstract>, upload: Upload) -> Self {
+        Self {
+            texture,
+            upload: Some(upload),
+        }
+    }
+
+    /// The texture, once the GPU has finished writing it.
+    pub fn poll(&mut self) -> Result<Option<Arc<dyn ImageViewAbstract>>, RenderError> {
+        if let Some(upload) = &self.upload {
+            if !upload.is_signaled()? {
+                return Ok(None);
+            }
+
+            // Returns straight away, releasing the staging buffer and marking the image
+            // as initialised so it can be sampled
+            upload.wait(None)?;
+            self.upload = None;
+        }
+
+        Ok(Some(self.texture.clone()))
+    }
+
+    /// Gives up on the upload after the device has been lost. Dropping it instead would
+    /// wait on a fence that is never signalled.
+    pub fn abandon(mut self) {
+        if let Some(upload) = self.upload.take() {
+            mem::forget(upload);
+        }
+    }
+}
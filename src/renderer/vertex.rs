@@ -18,3 +18,38 @@ impl Vertex {
         }
     }
 }
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Zeroable, Pod)]
+pub struct OverlayVertex {
+    pub position: [f32; 2],
+    pub texture: [f32; 2],
+    pub colour: [f32; 4],
+}
+
+impl_vertex!(OverlayVertex, position, texture, colour);
+
+impl OverlayVertex {
+    pub fn new(position: [f32; 2], texture: [f32; 2], colour: [f32; 4]) -> Self {
+        Self {
+            position,
+            texture,
+            colour,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Zeroable, Pod)]
+pub struct PrimitiveVertex {
+    pub position: [f32; 2],
+    pub colour: [f32; 4],
+}
+
+impl_vertex!(PrimitiveVertex, position, colour);
+
+impl PrimitiveVertex {
+    pub fn new(position: [f32; 2], colour: [f32; 4]) -> Self {
+        Self { position, colour }
+    }
+}
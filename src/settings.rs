@@ -0,0 +1,553 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs, io,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::{Args, Parser, Subcommand};
+use serde::Deserialize;
+
+use crate::{
+    app::Redraw,
+    coords::TilePos,
+    logging::{LogSettings, DEFAULT_FILTER},
+    renderer::{DeviceSelector, PresentMode, RendererSettings},
+    world::{ChunkKey, Preset, QueueLimits, WhenFull},
+};
+
+/// Read when present, if no other config file is given.
+const DEFAULT_CONFIG_PATH: &str = "worldviewer.toml";
+
+const DEFAULT_WINDOW_SIZE: (u32, u32) = (1280, 720);
+const DEFAULT_TICK_RATE: u32 = 60;
+const DEFAULT_UPLOAD_BUDGET: usize = 4;
+const DEFAULT_CHUNK_SIZE: u32 = 512;
+const DEFAULT_DISPLAY_SIZE: u32 = 300;
+
+/// Explore procedurally generated worlds.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    #[command(flatten)]
+    pub options: Options,
+
+    /// List the graphics devices, and whether each can be used, then exit
+    #[arg(long)]
+    pub list_devices: bool,
+
+    #[command(subcommand)]
+    pub mode: Option<Mode>,
+}
+
+#[derive(Subcommand)]
+pub enum Mode {
+    /// Open the interactive viewer (the default)
+    View,
+    /// Generate a rectangle of chunks and write it to a PPM image
+    Export(ExportArgs),
+    /// Generate a square of chunks without a window and report how long it took
+    Bench(BenchArgs),
+}
+
+/// Settings which can also be given in the config file.
+#[derive(Args)]
+pub struct Options {
+    /// Config file to read, defaulting to worldviewer.toml if it exists
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Window width in logical pixels
+    #[arg(long, global = true)]
+    pub width: Option<u32>,
+
+    /// Window height in logical pixels
+    #[arg(long, global = true)]
+    pub height: Option<u32>,
+
+    /// Simulation ticks per second
+    #[arg(long, global = true)]
+    pub tick_rate: Option<u32>,
+
+    /// When to draw frames
+    #[arg(long, global = true, value_enum)]
+    pub redraw: Option<Redraw>,
+
+    /// World seed, random if not given
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+
+    /// Tile to centre the view on at startup
+    #[arg(long, global = true, value_name = "X,Y", value_parser = parse_pair, allow_hyphen_values = true)]
+    pub start: Option<(i64, i64)>,
+
+    /// Width and height of a chunk in tiles
+    #[arg(long, global = true, value_name = "TILES")]
+    pub chunk_size: Option<u32>,
+
+    /// Width and height a chunk is drawn at in logical pixels
+    #[arg(long, global = true, value_name = "PIXELS")]
+    pub display_size: Option<u32>,
+
+    /// Number of generation threads
+    #[arg(long, global = true)]
+    pub workers: Option<NonZeroUsize>,
+
+    /// Most chunks to start uploading to the GPU between frames
+    #[arg(long, global = true, value_name = "CHUNKS")]
+    pub upload_budget: Option<NonZeroUsize>,
+
+    /// Most chunk requests to keep waiting for a generation thread
+    #[arg(long, global = true, value_name = "CHUNKS")]
+    pub request_queue: Option<NonZeroUsize>,
+
+    /// Most generated chunks to hold before the generation threads wait for them to be
+    /// collected
+    #[arg(long, global = true, value_name = "CHUNKS")]
+    pub result_queue: Option<NonZeroUsize>,
+
+    /// What happens to new requests once the request queue is full
+    #[arg(long, global = true, value_enum)]
+    pub when_full: Option<WhenFull>,
+
+    /// Terrain preset
+    #[arg(long, global = true, value_enum)]
+    pub preset: Option<Preset>,
+
+    /// World definition file adjusting the preset's terrain, reloaded by the viewer
+    /// whenever it changes
+    #[arg(long, global = true, value_name = "FILE")]
+    pub definition: Option<PathBuf>,
+
+    /// Graphics device to use, by index or part of its name, as shown by --list-devices
+    #[arg(long, global = true, value_name = "INDEX|NAME")]
+    pub device: Option<DeviceSelector>,
+
+    /// How frames are presented, falling back to fifo if unsupported
+    #[arg(long, global = true, value_enum)]
+    pub present_mode: Option<PresentMode>,
+
+    /// Number of swapchain images, limited to what the surface supports
+    #[arg(long, global = true)]
+    pub image_count: Option<u32>,
+
+    /// Enable the Vulkan validation layer, if installed, and log Vulkan's messages
+    #[arg(long, global = true, overrides_with = "no_vulkan_debug")]
+    pub vulkan_debug: bool,
+
+    /// Disable the Vulkan validation layer, even if the config file enables it
+    #[arg(long, global = true, overrides_with = "vulkan_debug")]
+    pub no_vulkan_debug: bool,
+
+    /// Log level or filter, such as debug or warn,worldviewer=trace, overriding RUST_LOG
+    #[arg(long, global = true, value_name = "FILTER")]
+    pub log_level: Option<String>,
+
+    /// Write a Chrome trace of generation and rendering to FILE, for chrome://tracing or
+    /// Perfetto
+    #[arg(long, global = true, value_name = "FILE")]
+    pub trace_file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// First chunk to export
+    #[arg(long, value_name = "X,Y", value_parser = parse_pair, allow_hyphen_values = true)]
+    pub from: (i64, i64),
+
+    /// Last chunk to export, inclusive
+    #[arg(long, value_name = "X,Y", value_parser = parse_pair, allow_hyphen_values = true)]
+    pub to: (i64, i64),
+
+    /// Image file to write
+    #[arg(short, long, value_name = "FILE")]
+    pub output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Width and height of the square, in chunks, starting from 0,0
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+    pub size: u32,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct ConfigFile {
+    width: Option<u32>,
+    height: Option<u32>,
+    tick_rate: Option<u32>,
+    redraw: Option<Redraw>,
+    seed: Option<u64>,
+    start: Option<(i64, i64)>,
+    chunk_size: Option<u32>,
+    display_size: Option<u32>,
+    workers: Option<NonZeroUsize>,
+    upload_budget: Option<NonZeroUsize>,
+    request_queue: Option<NonZeroUsize>,
+    result_queue: Option<NonZeroUsize>,
+    when_full: Option<WhenFull>,
+    preset: Option<Preset>,
+    definition: Option<PathBuf>,
+    device: Option<DeviceSelector>,
+    present_mode: Option<PresentMode>,
+    image_count: Option<u32>,
+    vulkan_debug: Option<bool>,
+    log_level: Option<String>,
+    trace_file: Option<PathBuf>,
+}
+
+/// Settings after combining the defaults, the config file and the command line.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub window_size: (u32, u32),
+    pub tick_rate: u32,
+    pub redraw: Redraw,
+    pub seed: u64,
+    pub start: TilePos,
+    pub chunk_size: u32,
+    pub display_size: u32,
+    pub workers: usize,
+    pub upload_budget: usize,
+    pub queue_limits: QueueLimits,
+    pub preset: Preset,
+    pub definition: Option<PathBuf>,
+    pub renderer: RendererSettings,
+    pub log: LogSettings,
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    UnableToReadConfig(PathBuf, io::Error),
+    InvalidConfig(PathBuf, toml::de::Error),
+    ZeroWindowSize,
+    ZeroTickRate,
+}
+
+impl Display for SettingsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::UnableToReadConfig(path, _) => {
+                write!(f, "unable to read {}", path.display())
+            }
+            SettingsError::InvalidConfig(path, _) => {
+                write!(f, "invalid config file {}", path.display())
+            }
+            SettingsError::ZeroWindowSize => write!(f, "window size must be at least 1x1"),
+            SettingsError::ZeroTickRate => write!(f, "tick rate must be at least one"),
+        }
+    }
+}
+
+impl Error for SettingsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SettingsError::UnableToReadConfig(_, e) => Some(e),
+            SettingsError::InvalidConfig(_, e) => Some(e),
+            SettingsError::ZeroWindowSize | SettingsError::ZeroTickRate => None,
+        }
+    }
+}
+
+impl Options {
+    /// Fills in anything not given on the command line from the config file, then from
+    /// the defaults. `rust_log` is the value of `RUST_LOG`, which comes between the
+    /// command line and the config file.
+    pub fn settings(&self, rust_log: Option<String>) -> Result<Settings, SettingsError> {
+        let file = match &self.config {
+            Some(path) => load_config(path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => load_config(DEFAULT_CONFIG_PATH)?,
+            None => ConfigFile::default(),
+        };
+
+        let window_size = (
+            self.width.or(file.width).unwrap_or(DEFAULT_WINDOW_SIZE.0),
+            self.height.or(file.height).unwrap_or(DEFAULT_WINDOW_SIZE.1),
+        );
+
+        if window_size.0 == 0 || window_size.1 == 0 {
+            return Err(SettingsError::ZeroWindowSize);
+        }
+
+        let tick_rate = self
+            .tick_rate
+            .or(file.tick_rate)
+            .unwrap_or(DEFAULT_TICK_RATE);
+
+        if tick_rate == 0 {
+            return Err(SettingsError::ZeroTickRate);
+        }
+
+        let (x, y) = self.start.or(file.start).unwrap_or((0, 0));
+        let limits = QueueLimits::default();
+
+        Ok(Settings {
+            window_size,
+            tick_rate,
+            redraw: self.redraw.or(file.redraw).unwrap_or_default(),
+            seed: self.seed.or(file.seed).unwrap_or_else(random_seed),
+            start: TilePos::new(x, y),
+            chunk_size: self
+                .chunk_size
+                .or(file.chunk_size)
+                .unwrap_or(DEFAULT_CHUNK_SIZE),
+            display_size: self
+                .display_size
+                .or(file.display_size)
+                .unwrap_or(DEFAULT_DISPLAY_SIZE),
+            workers: self
+                .workers
+                .or(file.workers)
+                .or_else(|| thread::available_parallelism().ok())
+                .map_or(1, NonZeroUsize::get),
+            upload_budget: self
+                .upload_budget
+                .or(file.upload_budget)
+                .map_or(DEFAULT_UPLOAD_BUDGET, NonZeroUsize::get),
+            queue_limits: QueueLimits {
+                requests: self
+                    .request_queue
+                    .or(file.request_queue)
+                    .map_or(limits.requests, NonZeroUsize::get),
+                results: self
+                    .result_queue
+                    .or(file.result_queue)
+                    .map_or(limits.results, NonZeroUsize::get),
+                when_full: self.when_full.or(file.when_full).unwrap_or_default(),
+            },
+            preset: self.preset.or(file.preset).unwrap_or_default(),
+            definition: self.definition.clone().or(file.definition),
+            renderer: RendererSettings {
+                device: self.device.clone().or(file.device),
+                present_mode: self.present_mode.or(file.present_mode).unwrap_or_default(),
+                image_count: self.image_count.or(file.image_count),
+                debug: self.vulkan_debug().or(file.vulkan_debug).unwrap_or(false),
+            },
+            log: LogSettings {
+                filter: self
+                    .log_level
+                    .clone()
+                    .or(rust_log)
+                    .or(file.log_level)
+                    .unwrap_or_else(|| DEFAULT_FILTER.into()),
+                trace_file: self.trace_file.clone().or(file.trace_file),
+            },
+        })
+    }
+
+    /// The last of --vulkan-debug and --no-vulkan-debug given, if either was.
+    fn vulkan_debug(&self) -> Option<bool> {
+        match (self.vulkan_debug, self.no_vulkan_debug) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl ExportArgs {
+    pub fn from(&self) -> ChunkKey {
+        ChunkKey::new(self.from.0, self.from.1)
+    }
+
+    pub fn to(&self) -> ChunkKey {
+        ChunkKey::new(self.to.0, self.to.1)
+    }
+}
+
+fn load_config<P: AsRef<Path>>(path: P) -> Result<ConfigFile, SettingsError> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)
+        .map_err(|e| SettingsError::UnableToReadConfig(path.to_path_buf(), e))?;
+
+    let mut file: ConfigFile = toml::from_str(&contents)
+        .map_err(|e| SettingsError::InvalidConfig(path.to_path_buf(), e))?;
+
+    // Paths in the file are relative to it, not to wherever the viewer was started from
+    if let Some(dir) = path.parent() {
+        file.definition = file.definition.map(|definition| dir.join(definition));
+        file.trace_file = file.trace_file.map(|trace_file| dir.join(trace_file));
+    }
+
+    Ok(file)
+}
+
+/// A seed from the clock, for when none is given.
+pub fn random_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64)
+}
+
+fn parse_pair(s: &str) -> Result<(i64, i64), String> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected X,Y but found '{s}'"))?;
+
+    let parse = |value: &str| {
+        value
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{}' is not a coordinate", value.trim()))
+    };
+
+    Ok((parse(x)?, parse(y)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn options_in(dir: &Path, config: &str, args: &[&str]) -> Options {
+        let path = dir.join("worldviewer.toml");
+        fs::write(&path, config).unwrap();
+
+        let config_arg = path.to_str().unwrap();
+        let cli = Cli::try_parse_from(
+            ["worldviewer", "--config", config_arg]
+                .into_iter()
+                .chain(args.iter().copied()),
+        )
+        .unwrap();
+
+        cli.options
+    }
+
+    fn settings_in(dir: &Path, config: &str, args: &[&str]) -> Result<Settings, SettingsError> {
+        options_in(dir, config, args).settings(None)
+    }
+
+    fn settings(config: &str, args: &[&str]) -> Result<Settings, SettingsError> {
+        settings_in(tempfile::tempdir().unwrap().path(), config, args)
+    }
+
+    #[test]
+    fn command_line_overrides_config_file_over_defaults() {
+        let config = r#"
+            width = 800
+            tick-rate = 30
+            seed = 5
+            chunk-size = 64
+            when-full = "reject"
+            vulkan-debug = true
+        "#;
+
+        let args = [
+            "--width",
+            "1024",
+            "--display-size",
+            "128",
+            "--no-vulkan-debug",
+        ];
+        let settings = settings(config, &args).unwrap();
+
+        assert_eq!(settings.window_size, (1024, DEFAULT_WINDOW_SIZE.1));
+        assert_eq!(settings.tick_rate, 30);
+        assert_eq!(settings.seed, 5);
+        assert_eq!((settings.chunk_size, settings.display_size), (64, 128));
+        assert_eq!(settings.queue_limits.when_full, WhenFull::Reject);
+        assert_eq!(
+            settings.queue_limits.requests,
+            QueueLimits::default().requests
+        );
+        assert_eq!(settings.upload_budget, DEFAULT_UPLOAD_BUDGET);
+        assert!(!settings.renderer.debug);
+    }
+
+    #[test]
+    fn vulkan_debug_takes_the_last_flag() {
+        let debug = |config: &str, args: &[&str]| settings(config, args).unwrap().renderer.debug;
+
+        assert!(!debug("", &[]));
+        assert!(debug("vulkan-debug = true", &[]));
+        assert!(debug("", &["--no-vulkan-debug", "--vulkan-debug"]));
+        assert!(!debug("", &["--vulkan-debug", "--no-vulkan-debug"]));
+    }
+
+    #[test]
+    fn log_filter_comes_from_command_line_then_environment_then_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = r#"log-level = "warn""#;
+        let filter = |args: &[&str], rust_log: Option<&str>| {
+            let options = options_in(dir.path(), config, args);
+            options
+                .settings(rust_log.map(Into::into))
+                .unwrap()
+                .log
+                .filter
+        };
+
+        assert_eq!(filter(&["--log-level", "trace"], Some("info")), "trace");
+        assert_eq!(filter(&[], Some("info")), "info");
+        assert_eq!(filter(&[], None), "warn");
+        assert_eq!(settings("", &[]).unwrap().log.filter, DEFAULT_FILTER);
+    }
+
+    #[test]
+    fn paths_in_config_file_are_relative_to_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let trace = env::temp_dir().join("trace.json");
+        let config = format!(
+            "definition = \"world.toml\"\ntrace-file = {:?}",
+            trace.to_str().unwrap()
+        );
+
+        let settings = settings_in(dir.path(), &config, &[]).unwrap();
+        assert_eq!(settings.definition, Some(dir.path().join("world.toml")));
+        assert_eq!(settings.log.trace_file, Some(trace));
+
+        // Paths given on the command line stay relative to the working directory
+        let settings = settings_in(dir.path(), &config, &["--definition", "other.toml"]).unwrap();
+        assert_eq!(settings.definition, Some(PathBuf::from("other.toml")));
+    }
+
+    #[test]
+    fn zero_sizes_and_rates_are_rejected() {
+        assert!(matches!(
+            settings("height = 0", &[]),
+            Err(SettingsError::ZeroWindowSize)
+        ));
+        assert!(matches!(
+            settings("", &["--width", "0"]),
+            Err(SettingsError::ZeroWindowSize)
+        ));
+        assert!(matches!(
+            settings("tick-rate = 0", &[]),
+            Err(SettingsError::ZeroTickRate)
+        ));
+        assert!(Cli::try_parse_from(["worldviewer", "--workers", "0"]).is_err());
+    }
+
+    #[test]
+    fn invalid_config_files_are_reported() {
+        assert!(matches!(
+            settings("colour = \"blue\"", &[]),
+            Err(SettingsError::InvalidConfig(..))
+        ));
+        assert!(matches!(
+            settings("width = -1", &[]),
+            Err(SettingsError::InvalidConfig(..))
+        ));
+
+        let missing = Cli::try_parse_from(["worldviewer", "--config", "missing.toml"]).unwrap();
+        assert!(matches!(
+            missing.options.settings(None),
+            Err(SettingsError::UnableToReadConfig(..))
+        ));
+    }
+
+    #[test]
+    fn pairs_parse_with_negatives_and_spaces() {
+        assert_eq!(parse_pair("3,-4"), Ok((3, -4)));
+        assert_eq!(parse_pair(" -1 , 2 "), Ok((-1, 2)));
+
+        for bad in ["", "3", "3;4", "a,1", "1,2,3", "1.5,2"] {
+            assert!(parse_pair(bad).is_err(), "{bad} parsed");
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::{mem, time::Instant};
+
+use egui::{
+    Context, Event, Key, Modifiers, MouseWheelUnit, PointerButton, Pos2, RawInput, Rect,
+    TexturesDelta, Vec2, ViewportId,
+};
+use stateloop::winit::event::{MouseScrollDelta, VirtualKeyCode};
+
+use crate::renderer::GuiBatch;
+
+/// Feeds window input to egui, which lays the interface out again on every run and
+/// reports what changed straight away.
+pub struct Ui {
+    context: Context,
+    events: Vec<Event>,
+    cursor: Option<Pos2>,
+    start: Instant,
+    /// Built up over runs until taken, so no texture update is lost between frames.
+    textures: TexturesDelta,
+    /// Whether egui asked to run again straight away, such as while animating.
+    repaint: bool,
+}
+
+impl Default for Ui {
+    fn default() -> Self {
+        Self {
+            context: Context::default(),
+            events: Vec::new(),
+            cursor: None,
+            start: Instant::now(),
+            textures: TexturesDelta::default(),
+            repaint: false,
+        }
+    }
+}
+
+impl Ui {
+    pub fn cursor_moved(&mut self, position: [f32; 2]) {
+        let position = Pos2::from(position);
+        self.cursor = Some(position);
+        self.events.push(Event::PointerMoved(position));
+    }
+
+    pub fn cursor_left(&mut self) {
+        self.cursor = None;
+        self.events.push(Event::PointerGone);
+    }
+
+    pub fn button(&mut self, down: bool) {
+        if let Some(pos) = self.cursor {
+            self.events.push(Event::PointerButton {
+                pos,
+                button: PointerButton::Primary,
+                pressed: down,
+                modifiers: Modifiers::NONE,
+            });
+        }
+    }
+
+    pub fn scroll(&mut self, delta: MouseScrollDelta) {
+        let (unit, delta) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => (MouseWheelUnit::Line, Vec2::new(x, y)),
+            MouseScrollDelta::PixelDelta(position) => (
+                MouseWheelUnit::Point,
+                Vec2::new(position.x as f32, position.y as f32),
+            ),
+        };
+
+        self.events.push(Event::MouseWheel {
+            unit,
+            delta,
+            modifiers: Modifiers::NONE,
+        });
+    }
+
+    pub fn text(&mut self, c: char) {
+        if !c.is_control() {
+            self.events.push(Event::Text(c.into()));
+        }
+    }
+
+    /// Passes on a press of one of the keys used for editing text, returning whether `code`
+    /// is one of them. It is released straight away, so that releases can go to whatever
+    /// the key was held down for.
+    pub fn key(&mut self, code: VirtualKeyCode) -> bool {
+        let key = match code {
+            VirtualKeyCode::Back => Key::Backspace,
+            VirtualKeyCode::Delete => Key::Delete,
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => Key::Enter,
+            VirtualKeyCode::Escape => Key::Escape,
+            VirtualKeyCode::Tab => Key::Tab,
+            VirtualKeyCode::Left => Key::ArrowLeft,
+            VirtualKeyCode::Right => Key::ArrowRight,
+            VirtualKeyCode::Up => Key::ArrowUp,
+            VirtualKeyCode::Down => Key::ArrowDown,
+            VirtualKeyCode::Home => Key::Home,
+            VirtualKeyCode::End => Key::End,
+            _ => return false,
+        };
+
+        for pressed in [true, false] {
+            self.events.push(Event::Key {
+                key,
+                physical_key: None,
+                pressed,
+                repeat: false,
+                modifiers: Modifiers::NONE,
+            });
+        }
+
+        true
+    }
+
+    /// Whether the pointer is over the interface or dragging part of it, so should not also
+    /// be acted on by whatever is underneath.
+    pub fn wants_mouse(&self) -> bool {
+        self.context.is_using_pointer() || self.context.is_pointer_over_area()
+    }
+
+    pub fn wants_keyboard(&self) -> bool {
+        self.context.wants_keyboard_input()
+    }
+
+    pub fn wants_repaint(&self) -> bool {
+        self.repaint
+    }
+
+    /// Lays the interface out over a window `size` logical pixels across, acting on the
+    /// input since the last run.
+    pub fn run<F>(&mut self, size: [f32; 2], pixels_per_point: f32, build: F) -> GuiBatch
+    where
+        F: FnMut(&Context),
+    {
+        let mut input = RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::from(size))),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            events: mem::take(&mut self.events),
+            focused: true,
+            ..RawInput::default()
+        };
+
+        input
+            .viewports
+            .entry(ViewportId::ROOT)
+            .or_default()
+            .native_pixels_per_point = Some(pixels_per_point);
+
+        let output = self.context.run(input, build);
+
+        self.textures.append(output.textures_delta);
+        self.repaint = output
+            .viewport_output
+            .get(&ViewportId::ROOT)
+            .is_some_and(|viewport| viewport.repaint_delay.is_zero());
+
+        GuiBatch {
+            primitives: self
+                .context
+                .tessellate(output.shapes, output.pixels_per_point),
+        }
+    }
+
+    /// Forgets input while the interface is hidden.
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.repaint = false;
+    }
+
+    pub fn take_textures(&mut self) -> TexturesDelta {
+        mem::take(&mut self.textures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: [f32; 2] = [400.0, 300.0];
+
+    fn run(ui: &mut Ui) -> (GuiBatch, Rect, bool) {
+        let (mut rect, mut clicked) = (Rect::NOTHING, false);
+
+        let batch = ui.run(SIZE, 1.0, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let response = ui.button("Click");
+                rect = response.rect;
+                clicked |= response.clicked();
+            });
+        });
+
+        (batch, rect, clicked)
+    }
+
+    #[test]
+    fn clicks_reach_widgets_under_the_cursor() {
+        let mut ui = Ui::default();
+
+        let (batch, rect, _) = run(&mut ui);
+        assert!(!batch.primitives.is_empty());
+        assert!(!ui.take_textures().set.is_empty());
+
+        ui.cursor_moved(rect.center().into());
+        run(&mut ui);
+        assert!(ui.wants_mouse());
+
+        ui.button(true);
+        assert!(!run(&mut ui).2);
+        ui.button(false);
+        assert!(run(&mut ui).2);
+
+        // egui keeps the last position for a pass after the pointer leaves
+        ui.cursor_left();
+        run(&mut ui);
+        run(&mut ui);
+        assert!(!ui.wants_mouse());
+    }
+
+    #[test]
+    fn only_editing_keys_are_taken() {
+        let mut ui = Ui::default();
+
+        assert!(ui.key(VirtualKeyCode::Back));
+        assert!(!ui.key(VirtualKeyCode::W));
+        ui.text('\r');
+        ui.text('4');
+
+        assert_eq!(ui.events.len(), 3);
+    }
+}
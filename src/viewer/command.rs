@@ -0,0 +1,264 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use clap::ValueEnum;
+
+use crate::{
+    coords::{TilePos, WorldPos, MAX_CHUNK, MAX_TILE},
+    world::{ChunkKey, Preset},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    Tile(TilePos),
+    Chunk(ChunkKey),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    GoTo(Target),
+    Bookmark(String),
+    Seed(u64),
+    Preset(Preset),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    Empty,
+    UnknownCommand(String),
+    InvalidCoordinate(String),
+    CoordinateOutOfRange(String),
+    MissingArgument(&'static str),
+    UnexpectedArgument(String),
+    InvalidSeed(String),
+    UnknownPreset(String),
+}
+
+impl Target {
+    pub fn centre(self, chunk_size: u32) -> WorldPos {
+        let (origin, size) = match self {
+            Target::Tile(tile) => (tile, 1.0),
+            Target::Chunk(key) => (TilePos::chunk_origin(key, chunk_size), chunk_size as f64),
+        };
+
+        let origin = origin.world();
+        WorldPos::new(origin.x + size / 2.0, origin.y + size / 2.0)
+    }
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Empty => write!(f, "no command given"),
+            CommandError::UnknownCommand(command) => write!(f, "unknown command '{command}'"),
+            CommandError::InvalidCoordinate(value) => write!(f, "'{value}' is not a coordinate"),
+            CommandError::CoordinateOutOfRange(value) => {
+                write!(f, "'{value}' is too far out to go to")
+            }
+            CommandError::MissingArgument(argument) => write!(f, "missing {argument}"),
+            CommandError::UnexpectedArgument(argument) => {
+                write!(f, "unexpected argument '{argument}'")
+            }
+            CommandError::InvalidSeed(value) => write!(f, "'{value}' is not a seed"),
+            CommandError::UnknownPreset(name) => write!(f, "unknown preset '{name}'"),
+        }
+    }
+}
+
+/// Accepts `[tile] X Y`, `chunk X Y`, `bookmark NAME`, `seed SEED` and `preset NAME`,
+/// where coordinates may also be separated by a comma.
+impl FromStr for Command {
+    type Err = CommandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (command, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+
+        match command {
+            "" => Err(CommandError::Empty),
+            "tile" | "t" => {
+                let (x, y) = coordinates(rest, MAX_TILE)?;
+                Ok(Command::GoTo(Target::Tile(TilePos::new(x, y))))
+            }
+            "chunk" | "c" => {
+                let (x, y) = coordinates(rest, MAX_CHUNK)?;
+                Ok(Command::GoTo(Target::Chunk(ChunkKey::new(x, y))))
+            }
+            "bookmark" | "b" => match rest.trim() {
+                "" => Err(CommandError::MissingArgument("bookmark name")),
+                name => Ok(Command::Bookmark(name.into())),
+            },
+            "seed" => match rest.trim() {
+                "" => Err(CommandError::MissingArgument("seed")),
+                seed => seed
+                    .parse()
+                    .map(Command::Seed)
+                    .map_err(|_| CommandError::InvalidSeed(seed.into())),
+            },
+            "preset" => match rest.trim() {
+                "" => Err(CommandError::MissingArgument("preset name")),
+                name => Preset::from_str(name, true)
+                    .map(Command::Preset)
+                    .map_err(|_| CommandError::UnknownPreset(name.into())),
+            },
+            _ if command.starts_with(|c: char| c == '-' || c.is_ascii_digit()) => {
+                let (x, y) = coordinates(s, MAX_TILE)?;
+                Ok(Command::GoTo(Target::Tile(TilePos::new(x, y))))
+            }
+            _ => Err(CommandError::UnknownCommand(command.into())),
+        }
+    }
+}
+
+/// Two coordinates no further from zero than `limit`.
+fn coordinates(s: &str, limit: i64) -> Result<(i64, i64), CommandError> {
+    let values = s
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>();
+
+    let parse = |index: usize, name| {
+        let value = values
+            .get(index)
+            .ok_or(CommandError::MissingArgument(name))?;
+
+        match value.parse::<i64>() {
+            Ok(coordinate) if coordinate.unsigned_abs() <= limit as u64 => Ok(coordinate),
+            Ok(_) => Err(CommandError::CoordinateOutOfRange(value.to_string())),
+            Err(_) => Err(CommandError::InvalidCoordinate(value.to_string())),
+        }
+    };
+
+    match values.get(2) {
+        Some(extra) => Err(CommandError::UnexpectedArgument(extra.to_string())),
+        None => Ok((parse(0, "x coordinate")?, parse(1, "y coordinate")?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Command, CommandError> {
+        s.parse()
+    }
+
+    fn tile(x: i64, y: i64) -> Result<Command, CommandError> {
+        Ok(Command::GoTo(Target::Tile(TilePos::new(x, y))))
+    }
+
+    fn chunk(x: i64, y: i64) -> Result<Command, CommandError> {
+        Ok(Command::GoTo(Target::Chunk(ChunkKey::new(x, y))))
+    }
+
+    #[test]
+    fn parses_tile_coordinates() {
+        assert_eq!(parse("tile 10 20"), tile(10, 20));
+        assert_eq!(parse("t 10,20"), tile(10, 20));
+        assert_eq!(parse("  -5, -7  "), tile(-5, -7));
+        assert_eq!(parse("3 4"), tile(3, 4));
+    }
+
+    #[test]
+    fn parses_chunk_coordinates() {
+        assert_eq!(parse("chunk 1 -2"), chunk(1, -2));
+        assert_eq!(parse("c -1,2"), chunk(-1, 2));
+    }
+
+    #[test]
+    fn rejects_bad_coordinates() {
+        assert_eq!(
+            parse("tile 1"),
+            Err(CommandError::MissingArgument("y coordinate"))
+        );
+        assert_eq!(
+            parse("chunk"),
+            Err(CommandError::MissingArgument("x coordinate"))
+        );
+        assert_eq!(
+            parse("tile 1 x"),
+            Err(CommandError::InvalidCoordinate("x".into()))
+        );
+        assert_eq!(
+            parse("1.5 2"),
+            Err(CommandError::InvalidCoordinate("1.5".into()))
+        );
+        assert_eq!(
+            parse("tile 1 2 3"),
+            Err(CommandError::UnexpectedArgument("3".into()))
+        );
+        assert_eq!(
+            parse("- 2"),
+            Err(CommandError::InvalidCoordinate("-".into()))
+        );
+    }
+
+    #[test]
+    fn rejects_coordinates_too_far_out() {
+        assert_eq!(parse("chunk 16777216 0"), chunk(MAX_CHUNK, 0));
+        assert_eq!(
+            parse("chunk 100000000000000000 0"),
+            Err(CommandError::CoordinateOutOfRange(
+                "100000000000000000".into()
+            ))
+        );
+        assert_eq!(
+            parse("tile 0 -1099511627777"),
+            Err(CommandError::CoordinateOutOfRange("-1099511627777".into()))
+        );
+        assert_eq!(
+            parse("-9223372036854775808 0"),
+            Err(CommandError::CoordinateOutOfRange(
+                "-9223372036854775808".into()
+            ))
+        );
+        assert_eq!(
+            parse("99999999999999999999 0"),
+            Err(CommandError::InvalidCoordinate(
+                "99999999999999999999".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn parses_other_commands() {
+        assert_eq!(
+            parse("bookmark  home base "),
+            Ok(Command::Bookmark("home base".into()))
+        );
+        assert_eq!(parse("seed 42"), Ok(Command::Seed(42)));
+        assert_eq!(parse("preset ice-age"), Ok(Command::Preset(Preset::IceAge)));
+
+        assert_eq!(parse(""), Err(CommandError::Empty));
+        assert_eq!(
+            parse("b"),
+            Err(CommandError::MissingArgument("bookmark name"))
+        );
+        assert_eq!(
+            parse("seed -1"),
+            Err(CommandError::InvalidSeed("-1".into()))
+        );
+        assert_eq!(
+            parse("preset moon"),
+            Err(CommandError::UnknownPreset("moon".into()))
+        );
+        assert_eq!(
+            parse("jump 1 2"),
+            Err(CommandError::UnknownCommand("jump".into()))
+        );
+    }
+
+    #[test]
+    fn targets_are_centred() {
+        assert_eq!(
+            Target::Tile(TilePos::new(-1, 2)).centre(16),
+            WorldPos::new(-0.5, 2.5)
+        );
+        assert_eq!(
+            Target::Chunk(ChunkKey::new(-1, 2)).centre(16),
+            WorldPos::new(-8.0, 40.0)
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+const FRAME_SAMPLES: usize = 60;
+
+/// Rolling average of the time between rendered frames.
+pub struct FrameTimer {
+    last: Option<Instant>,
+    samples: VecDeque<Duration>,
+}
+
+impl FrameTimer {
+    pub fn new() -> Self {
+        Self {
+            last: None,
+            samples: VecDeque::with_capacity(FRAME_SAMPLES),
+        }
+    }
+
+    pub fn frame(&mut self, now: Instant) {
+        if let Some(last) = self.last.replace(now) {
+            if self.samples.len() == FRAME_SAMPLES {
+                self.samples.pop_front();
+            }
+
+            self.samples.push_back(now.saturating_duration_since(last));
+        }
+    }
+
+    pub fn frame_time(&self) -> Duration {
+        match self.samples.len() {
+            0 => Duration::ZERO,
+            len => self.samples.iter().sum::<Duration>() / len as u32,
+        }
+    }
+
+    pub fn fps(&self) -> f64 {
+        match self.frame_time().as_secs_f64() {
+            secs if secs > 0.0 => 1.0 / secs,
+            _ => 0.0,
+        }
+    }
+}
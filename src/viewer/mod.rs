@@ -0,0 +1,832 @@
+use std::{
+    collections::{
+        hash_map::Entry::{Occupied, Vacant},
+        HashMap,
+    },
+    mem,
+    time::Duration,
+};
+
+use enumset::{EnumSet, EnumSetType};
+use tracing::debug;
+
+use crate::{
+    coords::{Camera, ScreenPos, TilePos, WorldPos},
+    world::{Chunk, ChunkKey, Focus, Palette, TileSample, WorldConfig},
+};
+
+pub use self::{
+    command::{Command, CommandError, Target},
+    hud::FrameTimer,
+};
+
+mod command;
+mod hud;
+
+const PAN_SPEED: f64 = 300.0;
+const PREFETCH_MARGIN: i64 = 1;
+const EVICTION_MARGIN: i64 = 3;
+/// How far out evicted chunks are still marked as such, before being forgotten.
+const FORGET_MARGIN: i64 = 6;
+const TOOLTIP_OFFSET: f64 = 16.0;
+const MINIMAP_SIZE: u32 = 200;
+const MINIMAP_MARGIN: f64 = 10.0;
+
+#[derive(Debug, EnumSetType)]
+pub enum InputState {
+    Up,
+    Down,
+    Right,
+    Left,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum ViewerEvent {
+    Pressed(InputState),
+    Released(InputState),
+    ToggleHud,
+    ToggleGrid,
+    CursorMoved(ScreenPos),
+    CursorLeft,
+    Clicked,
+    OpenPrompt,
+    PromptInput(char),
+    PromptBackspace,
+    CancelPrompt,
+    Resized { width: u32, height: u32 },
+}
+
+pub enum TextureEntry<T> {
+    /// Waiting for generation, drawing the texture from before the world changed if there
+    /// is one.
+    Requested(Option<T>),
+    Valid(T, Chunk),
+    Failed,
+    Evicted,
+    /// From before the world changed, drawn until it is requested and replaced.
+    Outdated(T),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChunkState {
+    Requested,
+    Valid,
+    Failed,
+    Evicted,
+    Outdated,
+}
+
+pub struct ChunkDraw<'a, T> {
+    pub position: ScreenPos,
+    pub texture: &'a T,
+}
+
+/// The corner minimap. Chunk and view positions are relative to its top-left corner.
+pub struct Minimap<'a, T> {
+    pub position: ScreenPos,
+    pub size: f64,
+    pub chunk_size: f64,
+    pub chunks: Vec<ChunkDraw<'a, T>>,
+    pub view_position: ScreenPos,
+    pub view_size: (f64, f64),
+}
+
+/// What a tick wants generated, nearest the centre first, and requests it no longer
+/// needs.
+pub struct ChunkRequests {
+    pub wanted: Vec<ChunkKey>,
+    /// Requested but gone well out of view, so worth withdrawing.
+    pub stale: Vec<ChunkKey>,
+}
+
+pub struct ChunkOutline {
+    pub key: ChunkKey,
+    pub position: ScreenPos,
+    pub size: f64,
+    pub state: Option<ChunkState>,
+}
+
+pub struct Viewer<T> {
+    config: WorldConfig,
+    input: EnumSet<InputState>,
+    camera: Camera,
+    textures: HashMap<ChunkKey, TextureEntry<T>>,
+    thumbnails: HashMap<ChunkKey, T>,
+    hud_visible: bool,
+    grid_visible: bool,
+    cursor: Option<ScreenPos>,
+    prompt: Option<String>,
+    status: Option<String>,
+}
+
+impl<T> TextureEntry<T> {
+    pub fn state(&self) -> ChunkState {
+        match self {
+            TextureEntry::Requested(_) => ChunkState::Requested,
+            TextureEntry::Valid(..) => ChunkState::Valid,
+            TextureEntry::Failed => ChunkState::Failed,
+            TextureEntry::Evicted => ChunkState::Evicted,
+            TextureEntry::Outdated(_) => ChunkState::Outdated,
+        }
+    }
+
+    fn texture(&self) -> Option<&T> {
+        match self {
+            TextureEntry::Valid(texture, _)
+            | TextureEntry::Requested(Some(texture))
+            | TextureEntry::Outdated(texture) => Some(texture),
+            _ => None,
+        }
+    }
+
+    fn into_texture(self) -> Option<T> {
+        match self {
+            TextureEntry::Valid(texture, _)
+            | TextureEntry::Requested(Some(texture))
+            | TextureEntry::Outdated(texture) => Some(texture),
+            _ => None,
+        }
+    }
+}
+
+impl<T> Viewer<T> {
+    pub fn new(config: WorldConfig, width: u32, height: u32) -> Self {
+        Self {
+            config,
+            input: EnumSet::empty(),
+            camera: Camera::new(WorldPos::new(0.0, 0.0), config.zoom(), (width, height)),
+            textures: HashMap::new(),
+            thumbnails: HashMap::new(),
+            hud_visible: true,
+            grid_visible: false,
+            cursor: None,
+            prompt: None,
+            status: None,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: ViewerEvent) {
+        match event {
+            // Key repeat sends presses for keys that are already held
+            ViewerEvent::Pressed(input) => {
+                if self.input.insert(input) {
+                    debug!(?input, held = ?self.input, "Input pressed");
+                }
+            }
+            ViewerEvent::Released(input) => {
+                if self.input.remove(input) {
+                    debug!(?input, held = ?self.input, "Input released");
+                }
+            }
+            ViewerEvent::ToggleHud => self.hud_visible = !self.hud_visible,
+            ViewerEvent::ToggleGrid => self.grid_visible = !self.grid_visible,
+            ViewerEvent::CursorMoved(position) => self.cursor = Some(position),
+            ViewerEvent::CursorLeft => self.cursor = None,
+            ViewerEvent::Clicked => {
+                if let Some(centre) = self.cursor.and_then(|cursor| self.minimap_target(cursor)) {
+                    self.camera.centre = centre;
+                }
+            }
+            ViewerEvent::OpenPrompt => {
+                // Keys held when the prompt opens would never see their release
+                if !self.input.is_empty() {
+                    debug!(released = ?self.input, "Input released by prompt");
+                }
+
+                self.input.clear();
+                self.prompt = Some(String::new());
+                self.status = None;
+            }
+            ViewerEvent::PromptInput(c) => {
+                if let Some(prompt) = &mut self.prompt {
+                    prompt.push(c);
+                }
+            }
+            ViewerEvent::PromptBackspace => {
+                if let Some(prompt) = &mut self.prompt {
+                    prompt.pop();
+                }
+            }
+            ViewerEvent::CancelPrompt => self.prompt = None,
+            ViewerEvent::Resized { width, height } => self.camera.viewport = (width, height),
+        }
+    }
+
+    pub fn tick(&mut self, delta: Duration) -> ChunkRequests {
+        let distance = PAN_SPEED * delta.as_secs_f64() / self.camera.zoom;
+
+        if self.input.contains(InputState::Up) {
+            self.camera.centre.y -= distance;
+        }
+
+        if self.input.contains(InputState::Down) {
+            self.camera.centre.y += distance;
+        }
+
+        if self.input.contains(InputState::Right) {
+            self.camera.centre.x += distance;
+        }
+
+        if self.input.contains(InputState::Left) {
+            self.camera.centre.x -= distance;
+        }
+
+        let mut requests = Vec::new();
+
+        let visible = match self.camera.visible_chunks(self.config.chunk_size()) {
+            Some(visible) => visible,
+            None => {
+                return ChunkRequests {
+                    wanted: requests,
+                    stale: Vec::new(),
+                }
+            }
+        };
+
+        for key in visible.expand(PREFETCH_MARGIN).iter() {
+            let previous = match self.textures.entry(key) {
+                Occupied(entry)
+                    if matches!(
+                        entry.get(),
+                        TextureEntry::Outdated(_) | TextureEntry::Evicted
+                    ) =>
+                {
+                    entry.remove().into_texture()
+                }
+                Occupied(_) => continue,
+                Vacant(_) => None,
+            };
+
+            self.textures.insert(key, TextureEntry::Requested(previous));
+            requests.push(key);
+        }
+
+        // Generation works through requests in order, so the chunks nearest the centre of
+        // the view, and so on screen, go first
+        let centre = self.camera.centre.tile().chunk(self.config.chunk_size());
+        requests.sort_by_key(|key| {
+            let (dx, dy) = (key.x - centre.x, key.y - centre.y);
+            dx * dx + dy * dy
+        });
+
+        // Chunks well outside the view are evicted, to be requested again if the view
+        // comes back, and forgotten further out. Requests still waiting out here are
+        // handed back to be withdrawn.
+        let retained = visible.expand(EVICTION_MARGIN);
+        let marked = visible.expand(FORGET_MARGIN);
+        let mut stale = Vec::new();
+
+        self.textures.retain(|&key, entry| match entry {
+            _ if retained.contains(key) => true,
+            TextureEntry::Requested(_) => {
+                stale.push(key);
+                false
+            }
+            _ => {
+                *entry = TextureEntry::Evicted;
+                marked.contains(key)
+            }
+        });
+
+        // Thumbnails last as long as they are near enough to be on the minimap
+        if let Some(minimap) = self
+            .minimap_camera()
+            .visible_chunks(self.config.chunk_size())
+        {
+            let retained = minimap.expand(EVICTION_MARGIN);
+            self.thumbnails.retain(|&key, _| retained.contains(key));
+        }
+
+        ChunkRequests {
+            wanted: requests,
+            stale,
+        }
+    }
+
+    /// Forgets a request the world did not keep, so it is made again on a later tick if
+    /// the chunk is still wanted.
+    pub fn drop_request(&mut self, key: ChunkKey) {
+        match self.textures.remove(&key) {
+            Some(TextureEntry::Requested(Some(texture))) => {
+                self.textures.insert(key, TextureEntry::Outdated(texture));
+            }
+            Some(TextureEntry::Requested(None)) | None => (),
+            Some(entry) => {
+                self.textures.insert(key, entry);
+            }
+        }
+    }
+
+    pub fn position(&self) -> TilePos {
+        self.camera.centre.tile()
+    }
+
+    pub fn focus(&self) -> Option<Focus> {
+        let chunk_size = self.config.chunk_size();
+
+        Some(Focus {
+            centre: self.camera.centre.tile().chunk(chunk_size),
+            visible: self.camera.visible_chunks(chunk_size)?,
+        })
+    }
+
+    pub fn is_panning(&self) -> bool {
+        !self.input.is_empty()
+    }
+
+    pub fn go_to(&mut self, target: Target) {
+        self.camera.centre = target.centre(self.config.chunk_size());
+    }
+
+    pub fn prompt_open(&self) -> bool {
+        self.prompt.is_some()
+    }
+
+    /// Closes the prompt and parses what was typed into it.
+    pub fn submit_prompt(&mut self) -> Option<Result<Command, CommandError>> {
+        self.prompt.take().map(|prompt| prompt.parse())
+    }
+
+    /// Sets a message to show in place of the prompt until it is next opened.
+    pub fn set_status<S: Into<String>>(&mut self, status: S) {
+        self.status = Some(status.into());
+    }
+
+    pub fn prompt_line(&self) -> Option<String> {
+        match &self.prompt {
+            Some(prompt) => Some(format!("> {prompt}_")),
+            None => self.status.clone(),
+        }
+    }
+
+    /// Stores a generated chunk. The thumbnail is kept for the minimap even once the full
+    /// texture has been evicted.
+    pub fn insert_chunk(&mut self, chunk: Chunk, texture: T, thumbnail: T) {
+        self.thumbnails.insert(chunk.key, thumbnail);
+        self.textures
+            .insert(chunk.key, TextureEntry::Valid(texture, chunk));
+    }
+
+    /// Records that a requested chunk could not be turned into a texture, so it is not
+    /// requested again.
+    pub fn fail_chunk(&mut self, key: ChunkKey) {
+        self.textures.insert(key, TextureEntry::Failed);
+    }
+
+    /// Forgets every chunk after switching to a different world, so the visible ones are
+    /// requested again on the next tick.
+    pub fn clear_chunks(&mut self) {
+        self.textures.clear();
+        self.thumbnails.clear();
+    }
+
+    /// For after the world has been adjusted rather than switched. Visible chunks are
+    /// requested again on the next tick, but their old textures are drawn until replaced
+    /// rather than leaving the view empty.
+    pub fn outdate_chunks(&mut self) {
+        self.thumbnails.clear();
+
+        for (key, entry) in mem::take(&mut self.textures) {
+            if let Some(texture) = entry.into_texture() {
+                self.textures.insert(key, TextureEntry::Outdated(texture));
+            }
+        }
+    }
+
+    /// For after the renderer has been rebuilt. The chunks stay requested until uploaded
+    /// again, and outdated textures are dropped to be requested again.
+    pub fn take_resident(&mut self) -> Vec<Chunk> {
+        self.thumbnails.clear();
+        self.textures
+            .retain(|_, entry| !matches!(entry, TextureEntry::Outdated(_)));
+
+        let mut resident = Vec::new();
+
+        for entry in self.textures.values_mut() {
+            if let TextureEntry::Valid(..) | TextureEntry::Requested(Some(_)) = entry {
+                if let TextureEntry::Valid(_, chunk) =
+                    mem::replace(entry, TextureEntry::Requested(None))
+                {
+                    resident.push(chunk);
+                }
+            }
+        }
+
+        resident
+    }
+
+    pub fn draw_list(&self) -> impl Iterator<Item = ChunkDraw<'_, T>> {
+        self.camera
+            .visible_chunks(self.config.chunk_size())
+            .into_iter()
+            .flat_map(|visible| visible.iter())
+            .filter_map(move |key| {
+                Some(ChunkDraw {
+                    position: self.camera.world_to_screen(
+                        TilePos::chunk_origin(key, self.config.chunk_size()).world(),
+                    ),
+                    texture: self.textures.get(&key)?.texture()?,
+                })
+            })
+    }
+
+    pub fn minimap(&self) -> Minimap<'_, T> {
+        let camera = self.minimap_camera();
+        let chunk_size = self.config.chunk_size();
+
+        let top_left =
+            camera.world_to_screen(self.camera.screen_to_world(ScreenPos::new(0.0, 0.0)));
+        let (width, height) = self.camera.viewport;
+
+        Minimap {
+            position: self.minimap_position(),
+            size: MINIMAP_SIZE as f64,
+            chunk_size: chunk_size as f64 * camera.zoom,
+            chunks: camera
+                .visible_chunks(chunk_size)
+                .into_iter()
+                .flat_map(|visible| visible.iter())
+                .filter_map(|key| {
+                    Some(ChunkDraw {
+                        position: camera
+                            .world_to_screen(TilePos::chunk_origin(key, chunk_size).world()),
+                        texture: self.thumbnails.get(&key)?,
+                    })
+                })
+                .collect(),
+            view_position: top_left,
+            view_size: (
+                width as f64 * camera.zoom / self.camera.zoom,
+                height as f64 * camera.zoom / self.camera.zoom,
+            ),
+        }
+    }
+
+    pub fn grid(&self) -> Vec<ChunkOutline> {
+        let chunk_size = self.config.chunk_size();
+
+        match self.camera.visible_chunks(chunk_size) {
+            Some(visible) if self.grid_visible => visible
+                .iter()
+                .map(|key| ChunkOutline {
+                    key,
+                    position: self
+                        .camera
+                        .world_to_screen(TilePos::chunk_origin(key, chunk_size).world()),
+                    size: chunk_size as f64 * self.camera.zoom,
+                    state: self.textures.get(&key).map(TextureEntry::state),
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn hud(&self, timer: &FrameTimer) -> Option<Vec<String>> {
+        if !self.hud_visible {
+            return None;
+        }
+
+        let tile = self.camera.centre.tile();
+        let chunk = tile.chunk(self.config.chunk_size());
+        let count = |state| {
+            self.textures
+                .values()
+                .filter(|entry| entry.state() == state)
+                .count()
+        };
+
+        Some(vec![
+            format!("Position: {}, {}", tile.x, tile.y),
+            format!("Chunk: {}, {}", chunk.x, chunk.y),
+            format!(
+                "FPS: {:.0} ({:.1} ms)",
+                timer.fps(),
+                timer.frame_time().as_secs_f64() * 1000.0
+            ),
+            format!("Pending chunks: {}", count(ChunkState::Requested)),
+            format!("Resident textures: {}", count(ChunkState::Valid)),
+        ])
+    }
+
+    pub fn tooltip<F>(&self, palette: &Palette, sample: F) -> Option<(ScreenPos, Vec<String>)>
+    where
+        F: FnOnce(TilePos) -> TileSample,
+    {
+        let cursor = self
+            .cursor
+            .filter(|&cursor| self.minimap_target(cursor).is_none())?;
+        let tile = self.camera.screen_to_world(cursor).tile();
+        let chunk_size = self.config.chunk_size();
+        let (x, y) = tile.within_chunk(chunk_size);
+
+        let kind = match self.textures.get(&tile.chunk(chunk_size)) {
+            Some(TextureEntry::Valid(_, chunk)) => chunk.tile(x, y),
+            _ => None,
+        };
+
+        let mut lines = vec![format!("Tile: {}, {}", tile.x, tile.y)];
+
+        match kind {
+            Some(kind) => {
+                lines.push(format!("Type: {}", kind.name()));
+                lines.push(format!("Colour: {}", palette.colour(kind)));
+            }
+            None => lines.push("Type: (not generated)".into()),
+        }
+
+        let sample = sample(tile);
+        lines.push(format!("Elevation: {:.4}", sample.elevation));
+
+        for (index, value) in sample.layers.iter().enumerate() {
+            lines.push(format!("Noise layer {}: {:.4}", index + 1, value));
+        }
+
+        Some((
+            ScreenPos::new(cursor.x + TOOLTIP_OFFSET, cursor.y + TOOLTIP_OFFSET),
+            lines,
+        ))
+    }
+
+    /// Shows a texel per thumbnail texel, centred on the main camera.
+    fn minimap_camera(&self) -> Camera {
+        Camera::new(
+            self.camera.centre,
+            self.config.thumbnail_size() as f64 / self.config.chunk_size() as f64,
+            (MINIMAP_SIZE, MINIMAP_SIZE),
+        )
+    }
+
+    fn minimap_position(&self) -> ScreenPos {
+        ScreenPos::new(
+            self.camera.viewport.0 as f64 - MINIMAP_SIZE as f64 - MINIMAP_MARGIN,
+            MINIMAP_MARGIN,
+        )
+    }
+
+    fn minimap_target(&self, cursor: ScreenPos) -> Option<WorldPos> {
+        let origin = self.minimap_position();
+        let local = ScreenPos::new(cursor.x - origin.x, cursor.y - origin.y);
+        let size = MINIMAP_SIZE as f64;
+
+        if (0.0..size).contains(&local.x) && (0.0..size).contains(&local.y) {
+            Some(self.minimap_camera().screen_to_world(local))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{ChunkTiming, TileKind};
+
+    const CHUNK_SIZE: u32 = 16;
+
+    // One pixel per tile, and a viewport that straddles the four chunks around the origin
+    fn viewer() -> Viewer<ChunkKey> {
+        Viewer::new(WorldConfig::new(CHUNK_SIZE, CHUNK_SIZE).unwrap(), 32, 32)
+    }
+
+    fn chunk(key: ChunkKey) -> Chunk {
+        let rows = vec![vec![TileKind::Ocean; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
+        Chunk::new(key, rows, ChunkTiming::default())
+    }
+
+    fn insert(viewer: &mut Viewer<ChunkKey>, key: ChunkKey) {
+        viewer.insert_chunk(chunk(key), key, key);
+    }
+
+    fn drawn(viewer: &Viewer<ChunkKey>) -> Vec<ChunkKey> {
+        let mut drawn = viewer
+            .draw_list()
+            .map(|draw| *draw.texture)
+            .collect::<Vec<_>>();
+        drawn.sort();
+        drawn
+    }
+
+    fn pan(viewer: &mut Viewer<ChunkKey>, input: InputState, seconds: f64) -> ChunkRequests {
+        viewer.handle_event(ViewerEvent::Pressed(input));
+        let requests = viewer.tick(Duration::from_secs_f64(seconds));
+        viewer.handle_event(ViewerEvent::Released(input));
+        requests
+    }
+
+    #[test]
+    fn requests_visible_and_prefetched_chunks_nearest_first() {
+        let mut viewer = viewer();
+        let requests = viewer.tick(Duration::ZERO);
+
+        assert_eq!(requests.wanted.len(), 16);
+        assert_eq!(requests.wanted[0], ChunkKey::new(0, 0));
+        assert!(
+            requests
+                .wanted
+                .windows(2)
+                .all(|pair| pair[0].x.pow(2) + pair[0].y.pow(2)
+                    <= pair[1].x.pow(2) + pair[1].y.pow(2))
+        );
+        assert!(requests.stale.is_empty());
+
+        assert!(viewer.tick(Duration::ZERO).wanted.is_empty());
+    }
+
+    #[test]
+    fn draws_inserted_chunks_where_they_are() {
+        let mut viewer = viewer();
+        viewer.tick(Duration::ZERO);
+        assert!(drawn(&viewer).is_empty());
+
+        insert(&mut viewer, ChunkKey::new(0, 0));
+
+        let draws = viewer.draw_list().collect::<Vec<_>>();
+        assert_eq!(draws.len(), 1);
+        assert_eq!(draws[0].position, ScreenPos::new(16.0, 16.0));
+    }
+
+    #[test]
+    fn pans_while_input_is_held() {
+        let mut viewer = viewer();
+
+        viewer.handle_event(ViewerEvent::Pressed(InputState::Right));
+        viewer.handle_event(ViewerEvent::Pressed(InputState::Down));
+        assert!(viewer.is_panning());
+
+        viewer.tick(Duration::from_secs(1));
+        assert_eq!(viewer.position(), TilePos::new(300, 300));
+
+        viewer.handle_event(ViewerEvent::Released(InputState::Right));
+        viewer.handle_event(ViewerEvent::Released(InputState::Down));
+        assert!(!viewer.is_panning());
+
+        viewer.tick(Duration::from_secs(1));
+        assert_eq!(viewer.position(), TilePos::new(300, 300));
+    }
+
+    #[test]
+    fn focuses_on_the_chunks_on_screen() {
+        let mut viewer = viewer();
+
+        let focus = viewer.focus().unwrap();
+        assert_eq!(focus.centre, ChunkKey::new(0, 0));
+        assert_eq!(focus.visible.min, ChunkKey::new(-1, -1));
+        assert_eq!(focus.visible.max, ChunkKey::new(0, 0));
+
+        viewer.handle_event(ViewerEvent::Resized {
+            width: 0,
+            height: 0,
+        });
+        assert!(viewer.focus().is_none());
+    }
+
+    #[test]
+    fn panning_away_withdraws_requests_and_forgets_chunks() {
+        let mut viewer = viewer();
+        let first = viewer.tick(Duration::ZERO).wanted;
+        insert(&mut viewer, ChunkKey::new(0, 0));
+
+        let requests = pan(&mut viewer, InputState::Right, 1.0);
+        let mut stale = requests.stale;
+        stale.sort();
+        let mut expected = first
+            .into_iter()
+            .filter(|&key| key != ChunkKey::new(0, 0))
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(stale, expected);
+        assert_eq!(viewer.textures.len(), requests.wanted.len());
+
+        let requests = pan(&mut viewer, InputState::Left, 1.0);
+        assert_eq!(requests.wanted.len(), 16);
+        assert!(requests.wanted.contains(&ChunkKey::new(0, 0)));
+    }
+
+    #[test]
+    fn thumbnails_are_kept_while_on_the_minimap() {
+        let mut viewer = viewer();
+        viewer.tick(Duration::ZERO);
+        insert(&mut viewer, ChunkKey::new(0, 0));
+
+        // The minimap is a couple of hundred chunks across, so still covers the chunk
+        pan(&mut viewer, InputState::Right, 1.0);
+        assert!(drawn(&viewer).is_empty());
+        assert_eq!(viewer.minimap().chunks.len(), 1);
+        assert_eq!(viewer.thumbnails.len(), 1);
+
+        pan(&mut viewer, InputState::Right, 10.0);
+        assert!(viewer.thumbnails.is_empty());
+    }
+
+    #[test]
+    fn evicted_chunks_are_marked_until_well_out_of_view() {
+        let mut viewer = viewer();
+        viewer.tick(Duration::ZERO);
+        insert(&mut viewer, ChunkKey::new(0, 0));
+        viewer.fail_chunk(ChunkKey::new(0, -1));
+
+        // Far enough for both to be evicted, but not forgotten
+        pan(&mut viewer, InputState::Right, 0.3);
+        for key in [ChunkKey::new(0, 0), ChunkKey::new(0, -1)] {
+            let state = viewer.textures.get(&key).map(TextureEntry::state);
+            assert_eq!(state, Some(ChunkState::Evicted));
+        }
+
+        // Failed chunks are given another go once they come back
+        let requests = pan(&mut viewer, InputState::Left, 0.3);
+        assert!(requests.wanted.contains(&ChunkKey::new(0, 0)));
+        assert!(requests.wanted.contains(&ChunkKey::new(0, -1)));
+
+        pan(&mut viewer, InputState::Right, 1.0);
+        assert!(!viewer.textures.contains_key(&ChunkKey::new(0, 0)));
+        assert!(!viewer.textures.contains_key(&ChunkKey::new(0, -1)));
+    }
+
+    #[test]
+    fn failed_chunks_are_not_requested_again() {
+        let mut viewer = viewer();
+        viewer.tick(Duration::ZERO);
+        viewer.fail_chunk(ChunkKey::new(0, 0));
+
+        viewer.outdate_chunks();
+        let requests = viewer.tick(Duration::ZERO);
+        assert_eq!(requests.wanted.len(), 16);
+
+        viewer.fail_chunk(ChunkKey::new(0, 0));
+        assert!(viewer.tick(Duration::ZERO).wanted.is_empty());
+    }
+
+    #[test]
+    fn dropped_requests_are_made_again() {
+        let mut viewer = viewer();
+        viewer.tick(Duration::ZERO);
+
+        viewer.drop_request(ChunkKey::new(0, 0));
+        assert_eq!(viewer.tick(Duration::ZERO).wanted, [ChunkKey::new(0, 0)]);
+
+        // A chunk that has arrived in the meantime is kept
+        insert(&mut viewer, ChunkKey::new(0, 0));
+        viewer.drop_request(ChunkKey::new(0, 0));
+        assert!(viewer.tick(Duration::ZERO).wanted.is_empty());
+        assert_eq!(drawn(&viewer), [ChunkKey::new(0, 0)]);
+    }
+
+    #[test]
+    fn outdated_chunks_are_drawn_until_replaced() {
+        let mut viewer = viewer();
+        viewer.tick(Duration::ZERO);
+        insert(&mut viewer, ChunkKey::new(0, 0));
+        insert(&mut viewer, ChunkKey::new(-1, 0));
+
+        viewer.outdate_chunks();
+        assert_eq!(drawn(&viewer), [ChunkKey::new(-1, 0), ChunkKey::new(0, 0)]);
+        assert!(viewer.minimap().chunks.is_empty());
+
+        let requests = viewer.tick(Duration::ZERO);
+        assert_eq!(requests.wanted.len(), 16);
+        assert_eq!(drawn(&viewer), [ChunkKey::new(-1, 0), ChunkKey::new(0, 0)]);
+
+        // A request dropped by the world leaves the old texture to be requested again
+        viewer.drop_request(ChunkKey::new(-1, 0));
+        assert_eq!(viewer.tick(Duration::ZERO).wanted, [ChunkKey::new(-1, 0)]);
+        assert_eq!(drawn(&viewer), [ChunkKey::new(-1, 0), ChunkKey::new(0, 0)]);
+    }
+
+    #[test]
+    fn cleared_chunks_are_requested_again() {
+        let mut viewer = viewer();
+        viewer.tick(Duration::ZERO);
+        insert(&mut viewer, ChunkKey::new(0, 0));
+        viewer.fail_chunk(ChunkKey::new(-1, 0));
+
+        viewer.clear_chunks();
+        assert!(drawn(&viewer).is_empty());
+        assert!(viewer.minimap().chunks.is_empty());
+        assert_eq!(viewer.tick(Duration::ZERO).wanted.len(), 16);
+    }
+
+    #[test]
+    fn take_resident_hands_back_valid_chunks() {
+        let mut viewer = viewer();
+        viewer.tick(Duration::ZERO);
+        insert(&mut viewer, ChunkKey::new(0, 0));
+        insert(&mut viewer, ChunkKey::new(-1, 0));
+        viewer.outdate_chunks();
+        viewer.tick(Duration::ZERO);
+        insert(&mut viewer, ChunkKey::new(0, 0));
+
+        let resident = viewer.take_resident();
+        assert_eq!(
+            resident.iter().map(|chunk| chunk.key).collect::<Vec<_>>(),
+            [ChunkKey::new(0, 0)]
+        );
+        assert!(drawn(&viewer).is_empty());
+        assert!(viewer.minimap().chunks.is_empty());
+
+        // The resident chunk stays requested while it is uploaded again, but the
+        // outdated one is requested afresh
+        assert!(viewer.tick(Duration::ZERO).wanted.is_empty());
+        viewer.drop_request(ChunkKey::new(-1, 0));
+        assert_eq!(viewer.tick(Duration::ZERO).wanted, [ChunkKey::new(-1, 0)]);
+    }
+}
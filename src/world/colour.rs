@@ -1,4 +1,15 @@
-#[derive(Debug, Copy, Clone)]
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::TileKind;
+
+/// Written as `#RRGGBB` in definition files.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
 pub struct Colour {
     r: u8,
     g: u8,
@@ -6,6 +17,12 @@ pub struct Colour {
     a: u8,
 }
 
+/// The colour each kind of tile is drawn in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Palette {
+    colours: [Colour; TileKind::COUNT],
+}
+
 impl Colour {
     pub fn new(r: u8, g: u8, b: u8) -> Self {
         Self {
@@ -19,4 +36,61 @@ impl Colour {
     pub fn as_array(self) -> [u8; 4] {
         [self.r, self.g, self.b, self.a]
     }
+
+    pub fn rgb(self) -> [u8; 3] {
+        [self.r, self.g, self.b]
+    }
+}
+
+impl Palette {
+    /// Colours in the order of `TileKind::ALL`.
+    pub fn new(colours: [Colour; TileKind::COUNT]) -> Self {
+        Self { colours }
+    }
+
+    pub fn colour(&self, kind: TileKind) -> Colour {
+        self.colours[kind as usize]
+    }
+
+    pub fn set_colour(&mut self, kind: TileKind, colour: Colour) {
+        self.colours[kind as usize] = colour;
+    }
+}
+
+impl Display for Colour {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
+
+impl FromStr for Colour {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{s}' is not a colour, expected #RRGGBB");
+        let hex = s
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6 && hex.is_ascii());
+        let hex = hex.ok_or_else(invalid)?;
+
+        let channel = |index: usize| {
+            u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).map_err(|_| invalid())
+        };
+
+        Ok(Self::new(channel(0)?, channel(1)?, channel(2)?))
+    }
+}
+
+impl TryFrom<String> for Colour {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Colour> for String {
+    fn from(colour: Colour) -> Self {
+        colour.to_string()
+    }
 }
@@ -0,0 +1,89 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+/// How many tiles along each side of a chunk share a single minimap texel.
+const THUMBNAIL_DOWNSAMPLE: u32 = 16;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WorldConfig {
+    chunk_size: u32,
+    display_size: u32,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    ZeroChunkSize,
+    ZeroDisplaySize,
+    ChunkSizeMismatch {
+        expected: u32,
+        width: usize,
+        height: usize,
+    },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ZeroChunkSize => write!(f, "chunk size must be at least one tile"),
+            ConfigError::ZeroDisplaySize => {
+                write!(f, "chunk display size must be at least one pixel")
+            }
+            ConfigError::ChunkSizeMismatch {
+                expected,
+                width,
+                height,
+            } => write!(
+                f,
+                "expected a {expected}x{expected} chunk but generated {width}x{height}"
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl WorldConfig {
+    /// `chunk_size` is the width and height of a chunk in tiles, and so also of its texture.
+    /// `display_size` is the width and height a chunk is drawn at, in logical pixels.
+    pub fn new(chunk_size: u32, display_size: u32) -> Result<Self, ConfigError> {
+        if chunk_size == 0 {
+            return Err(ConfigError::ZeroChunkSize);
+        }
+
+        if display_size == 0 {
+            return Err(ConfigError::ZeroDisplaySize);
+        }
+
+        Ok(Self {
+            chunk_size,
+            display_size,
+        })
+    }
+
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    pub fn display_size(&self) -> u32 {
+        self.display_size
+    }
+
+    /// Logical pixels per tile.
+    pub fn zoom(&self) -> f64 {
+        self.display_size as f64 / self.chunk_size as f64
+    }
+
+    pub fn texture_len(&self) -> usize {
+        self.chunk_size as usize * self.chunk_size as usize * 4
+    }
+
+    pub fn thumbnail_size(&self) -> u32 {
+        (self.chunk_size / THUMBNAIL_DOWNSAMPLE).max(1)
+    }
+
+    pub fn thumbnail_len(&self) -> usize {
+        self.thumbnail_size() as usize * self.thumbnail_size() as usize * 4
+    }
+}
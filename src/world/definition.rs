@@ -0,0 +1,428 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Colour, NoiseLayer, Preset, Terrain, TileKind};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WorldDefinition {
+    pub preset: Preset,
+    pub terrain: Terrain,
+}
+
+/// A definition file, where anything not given is taken from the preset.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct DefinitionFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preset: Option<Preset>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peak: Option<TileKind>,
+    #[serde(default)]
+    layers: Layers,
+    #[serde(default)]
+    thresholds: Thresholds,
+    #[serde(default)]
+    colours: Colours,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Layers {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base: Option<Layer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<Layer>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Layer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    step: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weight: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u32>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Thresholds {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ocean: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    beach: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grassland: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mountain: Option<f64>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Colours {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ocean: Option<Colour>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    beach: Option<Colour>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grassland: Option<Colour>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mountain: Option<Colour>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snow: Option<Colour>,
+}
+
+#[derive(Debug)]
+pub enum DefinitionError {
+    UnableToRead(PathBuf, io::Error),
+    InvalidDefinition(PathBuf, toml::de::Error),
+    UnableToWrite(PathBuf, io::Error),
+    UnableToSerialise(toml::ser::Error),
+    InvalidStep(&'static str, f64),
+    ZeroWeight(&'static str),
+    InvalidThreshold(TileKind, f64),
+    ThresholdsOutOfOrder(TileKind, TileKind),
+}
+
+impl Display for DefinitionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DefinitionError::UnableToRead(path, _) => {
+                write!(f, "unable to read {}", path.display())
+            }
+            DefinitionError::InvalidDefinition(path, _) => {
+                write!(f, "invalid world definition {}", path.display())
+            }
+            DefinitionError::UnableToWrite(path, _) => {
+                write!(f, "unable to write {}", path.display())
+            }
+            DefinitionError::UnableToSerialise(_) => {
+                write!(f, "unable to write out the world definition")
+            }
+            DefinitionError::InvalidStep(layer, step) => {
+                write!(f, "{layer} step must be a positive number, not {step}")
+            }
+            DefinitionError::ZeroWeight(layer) => write!(f, "{layer} weight must be at least one"),
+            DefinitionError::InvalidThreshold(kind, threshold) => write!(
+                f,
+                "{} threshold must be a number, not {threshold}",
+                kind.name().to_lowercase()
+            ),
+            DefinitionError::ThresholdsOutOfOrder(lower, higher) => write!(
+                f,
+                "{} threshold must be below the {} threshold",
+                lower.name().to_lowercase(),
+                higher.name().to_lowercase()
+            ),
+        }
+    }
+}
+
+impl Error for DefinitionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DefinitionError::UnableToRead(_, e) => Some(e),
+            DefinitionError::InvalidDefinition(_, e) => Some(e),
+            DefinitionError::UnableToWrite(_, e) => Some(e),
+            DefinitionError::UnableToSerialise(e) => Some(e),
+            DefinitionError::InvalidStep(..)
+            | DefinitionError::ZeroWeight(_)
+            | DefinitionError::InvalidThreshold(..)
+            | DefinitionError::ThresholdsOutOfOrder(..) => None,
+        }
+    }
+}
+
+impl WorldDefinition {
+    /// Reads a definition file, starting from `preset` unless the file names another.
+    pub fn load<P: AsRef<Path>>(path: P, preset: Preset) -> Result<Self, DefinitionError> {
+        let path = path.as_ref();
+        let contents =
+            fs::read_to_string(path).map_err(|e| DefinitionError::UnableToRead(path.into(), e))?;
+        let file = toml::from_str::<DefinitionFile>(&contents)
+            .map_err(|e| DefinitionError::InvalidDefinition(path.into(), e))?;
+
+        let preset = file.preset.unwrap_or(preset);
+        let mut terrain = preset.terrain();
+        terrain.peak = file.peak.unwrap_or(terrain.peak);
+        let layers = [file.layers.base, file.layers.detail];
+
+        for (layer, file) in terrain.layers.iter_mut().zip(layers) {
+            let file = file.unwrap_or_default();
+            layer.step = file.step.unwrap_or(layer.step);
+            layer.weight = file.weight.unwrap_or(layer.weight);
+            layer.seed = file.seed.unwrap_or(layer.seed);
+        }
+
+        let thresholds = [
+            file.thresholds.ocean,
+            file.thresholds.beach,
+            file.thresholds.grassland,
+            file.thresholds.mountain,
+        ];
+
+        for ((_, threshold), value) in terrain.thresholds.iter_mut().zip(thresholds) {
+            *threshold = value.unwrap_or(*threshold);
+        }
+
+        let colours = [
+            file.colours.ocean,
+            file.colours.beach,
+            file.colours.grassland,
+            file.colours.mountain,
+            file.colours.snow,
+        ];
+
+        for (kind, colour) in TileKind::ALL.into_iter().zip(colours) {
+            if let Some(colour) = colour {
+                terrain.palette.set_colour(kind, colour);
+            }
+        }
+
+        let definition = Self { preset, terrain };
+        definition.validate()?;
+        Ok(definition)
+    }
+
+    /// Writes out every setting, so the file reads the same whatever preset it is loaded
+    /// with.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), DefinitionError> {
+        let path = path.as_ref();
+        let terrain = &self.terrain;
+        let layer = |layer: &NoiseLayer| {
+            Some(Layer {
+                step: Some(layer.step),
+                weight: Some(layer.weight),
+                seed: Some(layer.seed),
+            })
+        };
+        let [(_, ocean), (_, beach), (_, grassland), (_, mountain)] = terrain.thresholds;
+        let colour = |kind| Some(terrain.palette.colour(kind));
+
+        let file = DefinitionFile {
+            preset: Some(self.preset),
+            peak: Some(terrain.peak),
+            layers: Layers {
+                base: layer(&terrain.layers[0]),
+                detail: layer(&terrain.layers[1]),
+            },
+            thresholds: Thresholds {
+                ocean: Some(ocean),
+                beach: Some(beach),
+                grassland: Some(grassland),
+                mountain: Some(mountain),
+            },
+            colours: Colours {
+                ocean: colour(TileKind::Ocean),
+                beach: colour(TileKind::Beach),
+                grassland: colour(TileKind::Grassland),
+                mountain: colour(TileKind::Mountain),
+                snow: colour(TileKind::Snow),
+            },
+        };
+
+        let contents = toml::to_string_pretty(&file).map_err(DefinitionError::UnableToSerialise)?;
+        fs::write(path, contents).map_err(|e| DefinitionError::UnableToWrite(path.into(), e))
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.terrain != self.preset.terrain()
+    }
+
+    pub fn validate(&self) -> Result<(), DefinitionError> {
+        for (name, layer) in NoiseLayer::NAMES.into_iter().zip(&self.terrain.layers) {
+            if !layer.step.is_finite() || layer.step <= 0.0 {
+                return Err(DefinitionError::InvalidStep(name, layer.step));
+            }
+
+            if layer.weight == 0 {
+                return Err(DefinitionError::ZeroWeight(name));
+            }
+        }
+
+        for &(kind, threshold) in &self.terrain.thresholds {
+            if !threshold.is_finite() {
+                return Err(DefinitionError::InvalidThreshold(kind, threshold));
+            }
+        }
+
+        for pair in self.terrain.thresholds.windows(2) {
+            let ((lower, low), (higher, high)) = (pair[0], pair[1]);
+
+            if low >= high {
+                return Err(DefinitionError::ThresholdsOutOfOrder(lower, higher));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<Preset> for WorldDefinition {
+    fn from(preset: Preset) -> Self {
+        Self {
+            preset,
+            terrain: preset.terrain(),
+        }
+    }
+}
+
+/// The preset's name, marked if the terrain has been changed from it.
+impl Display for WorldDefinition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.preset.name())?;
+
+        if self.is_modified() {
+            write!(f, " (modified)")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(contents: &str, preset: Preset) -> Result<WorldDefinition, DefinitionError> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("world.toml");
+        fs::write(&path, contents).unwrap();
+
+        WorldDefinition::load(&path, preset)
+    }
+
+    #[test]
+    fn loads_changes_over_the_preset() {
+        let contents = r##"
+            peak = "mountain"
+
+            [layers.detail]
+            weight = 3
+
+            [thresholds]
+            ocean = -0.4
+
+            [colours]
+            snow = "#FF8000"
+        "##;
+
+        let definition = load(contents, Preset::Pangaea).unwrap();
+        let terrain = definition.terrain;
+        let preset = Preset::Pangaea.terrain();
+
+        assert_eq!(definition.preset, Preset::Pangaea);
+        assert_eq!(terrain.peak, TileKind::Mountain);
+        assert_eq!(terrain.layers[0], preset.layers[0]);
+        assert_eq!(terrain.layers[1].weight, 3);
+        assert_eq!(terrain.layers[1].step, preset.layers[1].step);
+        assert_eq!(terrain.thresholds[0], (TileKind::Ocean, -0.4));
+        assert_eq!(terrain.thresholds[1], preset.thresholds[1]);
+        assert_eq!(
+            terrain.palette.colour(TileKind::Snow),
+            Colour::new(255, 128, 0)
+        );
+        assert_eq!(
+            terrain.palette.colour(TileKind::Ocean),
+            preset.palette.colour(TileKind::Ocean)
+        );
+        assert!(definition.is_modified());
+    }
+
+    #[test]
+    fn named_preset_overrides_the_one_given() {
+        let definition = load(r#"preset = "ice-age""#, Preset::Default).unwrap();
+
+        assert_eq!(definition, Preset::IceAge.into());
+        assert!(!definition.is_modified());
+    }
+
+    #[test]
+    fn invalid_files_are_rejected() {
+        for contents in [
+            "unknown = 1",
+            "[thresholds]\nsnow = 0.5",
+            "[layers.base]\nweight = -1",
+            "[colours]\nocean = \"blue\"",
+            "peak = \"lava\"",
+        ] {
+            assert!(
+                matches!(
+                    load(contents, Preset::Default),
+                    Err(DefinitionError::InvalidDefinition(..))
+                ),
+                "{contents}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_terrain_is_rejected() {
+        let check = |contents| load(contents, Preset::Default).unwrap_err();
+
+        assert!(matches!(
+            check("[layers.base]\nstep = 0.0"),
+            DefinitionError::InvalidStep("base", _)
+        ));
+        assert!(matches!(
+            check("[layers.detail]\nstep = nan"),
+            DefinitionError::InvalidStep("detail", _)
+        ));
+        assert!(matches!(
+            check("[layers.detail]\nweight = 0"),
+            DefinitionError::ZeroWeight("detail")
+        ));
+        assert!(matches!(
+            check("[thresholds]\nbeach = inf"),
+            DefinitionError::InvalidThreshold(TileKind::Beach, _)
+        ));
+        assert!(matches!(
+            check("[thresholds]\ngrassland = 0.9"),
+            DefinitionError::ThresholdsOutOfOrder(TileKind::Grassland, TileKind::Mountain)
+        ));
+    }
+
+    #[test]
+    fn saved_definitions_load_the_same_with_any_preset() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("world.toml");
+
+        let mut definition = WorldDefinition::from(Preset::Archipelago);
+        definition.terrain.layers[0].step = 0.0123;
+        definition.terrain.layers[1].seed = 42;
+        definition.terrain.thresholds[3].1 = 0.7;
+        definition.terrain.peak = TileKind::Beach;
+        definition
+            .terrain
+            .palette
+            .set_colour(TileKind::Grassland, Colour::new(1, 2, 3));
+
+        definition.save(&path).unwrap();
+
+        assert_eq!(
+            WorldDefinition::load(&path, Preset::Default).unwrap(),
+            definition
+        );
+    }
+
+    #[test]
+    fn missing_files_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(matches!(
+            WorldDefinition::load(dir.path().join("missing.toml"), Preset::Default),
+            Err(DefinitionError::UnableToRead(..))
+        ));
+        assert!(matches!(
+            WorldDefinition::from(Preset::Default).save(dir.path()),
+            Err(DefinitionError::UnableToWrite(..))
+        ));
+    }
+}
@@ -1,11 +1,37 @@
-use std::thread::JoinHandle;
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io,
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::Instant,
+};
 
-use crossbeam_channel::{Receiver, Sender};
+use crossbeam_channel::Receiver;
+use tracing::{debug, trace};
 
-pub use self::colour::Colour;
+use crate::coords::TilePos;
+
+pub use self::{
+    colour::{Colour, Palette},
+    config::{ConfigError, WorldConfig},
+    definition::{DefinitionError, WorldDefinition},
+    preset::{NoiseLayer, Preset, Terrain},
+    queue::{Focus, QueueCounts, QueueLimits, Requested, WhenFull},
+    stats::{ChunkTiming, Latency, WorldStats},
+    texels::{ChunkTexels, HeapTexels, TexelPool},
+    tile::{TileKind, TileSample},
+};
 
 mod colour;
+mod config;
+mod definition;
+mod preset;
+mod queue;
+mod stats;
 mod task;
+mod texels;
+mod tile;
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
 pub struct ChunkKey {
@@ -13,76 +39,342 @@ pub struct ChunkKey {
     pub y: i64,
 }
 
+/// A chunk's tiles, stored row by row in a single buffer.
 pub struct Chunk {
     pub key: ChunkKey,
-    pub data: Vec<Vec<Colour>>,
+    pub timing: ChunkTiming,
+    width: usize,
+    height: usize,
+    tiles: Vec<TileKind>,
+}
+
+/// A chunk straight from the generation threads, with its texels already written out
+/// unless it did not match the config.
+pub struct GeneratedChunk<B> {
+    pub chunk: Chunk,
+    pub texels: Result<ChunkTexels<B>, ConfigError>,
+    epoch: u64,
 }
 
-pub struct World {
-    tx: Sender<ChunkKey>,
-    rx: Receiver<Chunk>,
+pub struct World<P: TexelPool = HeapTexels> {
+    requests: Arc<queue::RequestQueue>,
+    rx: Receiver<GeneratedChunk<P::Buffer>>,
+    limits: QueueLimits,
+    params: Arc<Mutex<task::Params>>,
+    recorder: Arc<Mutex<stats::Recorder>>,
+    seed: u64,
+    definition: WorldDefinition,
+    noise: task::Noise,
     _thread: JoinHandle<()>,
 }
 
-struct SizedIteratorWrapper<T, I: Iterator<Item = T>> {
-    inner: I,
-    size: usize,
+#[derive(Debug)]
+pub enum WorldError {
+    UnableToStartGeneration(io::Error),
+    GenerationStopped,
 }
 
-impl<T, I: Iterator<Item = T>> SizedIteratorWrapper<T, I> {
-    fn new(inner: I, size: usize) -> Self {
-        Self { inner, size }
+impl Display for WorldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            WorldError::UnableToStartGeneration(_) => {
+                write!(f, "unable to start the generation thread")
+            }
+            WorldError::GenerationStopped => write!(f, "the generation threads have stopped"),
+        }
     }
 }
 
-impl<T, I: Iterator<Item = T>> Iterator for SizedIteratorWrapper<T, I> {
-    type Item = T;
+impl Error for WorldError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WorldError::UnableToStartGeneration(e) => Some(e),
+            WorldError::GenerationStopped => None,
+        }
+    }
+}
+
+impl<P: TexelPool> World<P> {
+    /// Starts generating on `workers` threads, which must be at least one, writing
+    /// textures into buffers from `pool`. `notify` is called from those threads whenever a
+    /// chunk is ready to be collected. Neither queue grows beyond `limits`.
+    pub fn new<N>(
+        config: WorldConfig,
+        seed: u64,
+        definition: WorldDefinition,
+        workers: usize,
+        limits: QueueLimits,
+        pool: P,
+        notify: N,
+    ) -> Result<Self, WorldError>
+    where
+        N: Fn() + Send + Sync + 'static,
+    {
+        let requests = Arc::new(queue::RequestQueue::new(limits.requests));
+        let (result_tx, result_rx) = crossbeam_channel::bounded(limits.results);
+        let noise = task::Noise::new(seed, &definition.terrain);
+        let params = Arc::new(Mutex::new(task::Params {
+            epoch: 0,
+            terrain: definition.terrain,
+            noise,
+        }));
+        let recorder = Arc::new(Mutex::new(stats::Recorder::default()));
+        let output = task::Output {
+            tx: result_tx,
+            recorder: recorder.clone(),
+            pool,
+            notify: Box::new(notify),
+        };
+
+        let thread_requests = requests.clone();
+        let thread_params = params.clone();
+        let thread = std::thread::Builder::new()
+            .name("World Viewer Generation Thread".into())
+            .spawn(move || {
+                task::worldgen_task(config, workers, &thread_requests, &thread_params, output)
+            })
+            .map_err(WorldError::UnableToStartGeneration)?;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next()
+        Ok(Self {
+            _thread: thread,
+            requests,
+            rx: result_rx,
+            limits,
+            params,
+            recorder,
+            seed,
+            definition,
+            noise,
+        })
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.size, Some(self.size))
+    pub fn seed(&self) -> u64 {
+        self.seed
     }
-}
 
-impl<T, I: Iterator<Item = T>> ExactSizeIterator for SizedIteratorWrapper<T, I> {}
+    pub fn definition(&self) -> WorldDefinition {
+        self.definition
+    }
 
-impl World {
-    pub fn new() -> Self {
-        let (request_tx, request_rx) = crossbeam_channel::unbounded();
-        let (result_tx, result_rx) = crossbeam_channel::unbounded();
+    /// Increases whenever the world is reconfigured.
+    pub fn epoch(&self) -> u64 {
+        self.params.lock().unwrap().epoch
+    }
 
-        Self {
-            _thread: std::thread::Builder::new()
-                .name("World Viewer Generation Thread".into())
-                .spawn(move || task::worldgen_task(request_rx, result_tx))
-                .unwrap(),
-            tx: request_tx,
-            rx: result_rx,
+    /// Generates from `seed` and `definition` from now on. Requests still waiting are
+    /// dropped, and chunks already being generated are discarded once they finish, so
+    /// every result collected afterwards comes from the new settings.
+    pub fn reconfigure(&mut self, seed: u64, definition: WorldDefinition) {
+        let noise = task::Noise::new(seed, &definition.terrain);
+
+        let epoch = {
+            let mut params = self.params.lock().unwrap();
+            *params = task::Params {
+                epoch: params.epoch + 1,
+                terrain: definition.terrain,
+                noise,
+            };
+            params.epoch
+        };
+
+        self.requests.clear();
+        self.seed = seed;
+        self.definition = definition;
+        self.noise = noise;
+
+        debug!(epoch, seed, %definition, "Reconfigured world");
+    }
+
+    /// Queues `key` for generation, unless it is already queued or the queue is full. A
+    /// blocking request gives up if the result queue fills, as nothing would make room
+    /// until the caller collects them.
+    pub fn request_chunk(&self, key: ChunkKey) -> Requested {
+        let request = task::Request {
+            key,
+            time: Instant::now(),
+            epoch: self.epoch(),
+        };
+        let requested = self
+            .requests
+            .push(request, self.limits.when_full, || self.rx.is_full());
+
+        trace!(x = key.x, y = key.y, ?requested, "Requested chunk");
+        requested
+    }
+
+    /// Withdraws the request for `key` if no generation thread has started on it, such
+    /// as once it has gone well out of view.
+    pub fn cancel_chunk(&self, key: ChunkKey) -> bool {
+        self.requests.cancel(key)
+    }
+
+    /// Moves the view that a full request queue keeps requests nearest to.
+    pub fn set_focus(&self, focus: Focus) {
+        self.requests.set_focus(focus);
+    }
+
+    pub fn limits(&self) -> QueueLimits {
+        self.limits
+    }
+
+    /// Whether any generated chunks are waiting to be collected.
+    pub fn has_results(&self) -> bool {
+        !self.rx.is_empty()
+    }
+
+    pub fn get_chunk_result(&self) -> Option<GeneratedChunk<P::Buffer>> {
+        self.rx
+            .try_iter()
+            .find(|generated| self.is_current(generated))
+    }
+
+    /// Blocks until the next requested chunk has been generated, failing if the
+    /// generation threads have stopped.
+    pub fn wait_chunk_result(&self) -> Result<GeneratedChunk<P::Buffer>, WorldError> {
+        self.rx
+            .iter()
+            .find(|generated| self.is_current(generated))
+            .ok_or(WorldError::GenerationStopped)
+    }
+
+    /// Whether a result was generated since the last reconfiguration, logging it if not.
+    fn is_current(&self, generated: &GeneratedChunk<P::Buffer>) -> bool {
+        let key = generated.chunk.key;
+        let current = generated.epoch == self.epoch();
+
+        if !current {
+            trace!(x = key.x, y = key.y, "Discarded chunk from an older epoch");
+        }
+
+        current
+    }
+
+    /// Generates every chunk in `keys`, passing each to `f` as it completes, in whatever
+    /// order they finish. No more are requested at once than the request queue holds, so
+    /// none are turned away.
+    pub fn generate_all<I, F, E>(&self, keys: I, mut f: F) -> Result<(), E>
+    where
+        I: IntoIterator<Item = ChunkKey>,
+        F: FnMut(GeneratedChunk<P::Buffer>) -> Result<(), E>,
+        E: From<WorldError>,
+    {
+        let mut outstanding = 0;
+
+        for key in keys {
+            if outstanding == self.limits.requests {
+                f(self.wait_chunk_result()?)?;
+                outstanding -= 1;
+            }
+
+            if self.request_chunk(key) != Requested::Coalesced {
+                outstanding += 1;
+            }
+        }
+
+        for _ in 0..outstanding {
+            f(self.wait_chunk_result()?)?;
         }
+
+        Ok(())
+    }
+
+    pub fn sample(&self, tile: TilePos) -> TileSample {
+        self.noise.sample(tile.x, tile.y)
     }
 
-    pub fn request_chunk(&self, key: ChunkKey) {
-        self.tx.send(key).unwrap();
+    /// Timings of the most recently generated chunks, and how many are still waiting.
+    pub fn stats(&self) -> WorldStats {
+        self.recorder.lock().unwrap().stats(
+            self.requests.len(),
+            self.rx.len(),
+            self.requests.counts(),
+        )
     }
+}
 
-    pub fn get_chunk_result(&self) -> Option<Chunk> {
-        self.rx.try_recv().ok()
+impl<P: TexelPool> Drop for World<P> {
+    fn drop(&mut self) {
+        self.requests.close();
     }
 }
 
 impl Chunk {
-    pub fn new(key: ChunkKey, data: Vec<Vec<Colour>>) -> Self {
-        Self { key, data }
+    pub fn new(key: ChunkKey, rows: Vec<Vec<TileKind>>, timing: ChunkTiming) -> Self {
+        let height = rows.len();
+        let width = rows.first().map_or(0, Vec::len);
+
+        // Ragged rows are kept as the first width that differs, so they fail validation
+        let width = rows
+            .iter()
+            .map(Vec::len)
+            .find(|&len| len != width)
+            .unwrap_or(width);
+
+        Self {
+            key,
+            timing,
+            width,
+            height,
+            tiles: rows.into_iter().flatten().collect(),
+        }
     }
 
-    pub fn texture(self) -> impl Iterator<Item = u8> + ExactSizeIterator {
-        SizedIteratorWrapper::new(
-            self.data.into_iter().flatten().flat_map(Colour::as_array),
-            512 * 512 * 4,
-        )
+    pub fn tile(&self, x: u32, y: u32) -> Option<TileKind> {
+        let (x, y) = (x as usize, y as usize);
+
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        self.tiles.get(y * self.width + x).copied()
+    }
+
+    /// Writes the full texture and minimap thumbnail into buffers from `pool`, in the
+    /// colours of `palette`.
+    pub fn texels<P: TexelPool>(
+        &self,
+        config: &WorldConfig,
+        palette: &Palette,
+        pool: &P,
+    ) -> Result<ChunkTexels<P::Buffer>, ConfigError> {
+        self.validate(config)?;
+
+        let texture = pool.fill(config.texture_len(), |texels| {
+            for (texel, tile) in texels.chunks_exact_mut(4).zip(&self.tiles) {
+                texel.copy_from_slice(&palette.colour(*tile).as_array());
+            }
+        });
+
+        // A downsampled texture, taking the nearest tile for each texel
+        let size = config.thumbnail_size() as usize;
+        let stride = config.chunk_size() as usize / size;
+
+        let thumbnail = pool.fill(config.thumbnail_len(), |texels| {
+            for (i, texel) in texels.chunks_exact_mut(4).enumerate() {
+                let (x, y) = (i % size * stride, i / size * stride);
+                let tile = self.tiles[y * self.width + x];
+                texel.copy_from_slice(&palette.colour(tile).as_array());
+            }
+        });
+
+        Ok(ChunkTexels { texture, thumbnail })
+    }
+
+    fn validate(&self, config: &WorldConfig) -> Result<(), ConfigError> {
+        let expected = config.chunk_size() as usize;
+
+        if self.width != expected
+            || self.height != expected
+            || self.tiles.len() != expected * expected
+        {
+            return Err(ConfigError::ChunkSizeMismatch {
+                expected: config.chunk_size(),
+                width: self.width,
+                height: self.height,
+            });
+        }
+
+        Ok(())
     }
 }
 
@@ -91,3 +383,65 @@ impl ChunkKey {
         Self { x, y }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_with(definition: WorldDefinition) -> World {
+        World::new(
+            WorldConfig::new(16, 16).unwrap(),
+            1,
+            definition,
+            2,
+            QueueLimits::default(),
+            HeapTexels,
+            || (),
+        )
+        .unwrap()
+    }
+
+    fn world() -> World {
+        world_with(Preset::Default.into())
+    }
+
+    #[test]
+    fn reconfiguring_discards_older_results() {
+        let mut world = world();
+
+        for x in 0..8 {
+            world.request_chunk(ChunkKey::new(x, 0));
+        }
+
+        world.reconfigure(2, Preset::Archipelago.into());
+        assert_eq!(world.epoch(), 1);
+
+        // Anything from before has been withdrawn, or is thrown away as it arrives
+        world.request_chunk(ChunkKey::new(100, 100));
+        let generated = world.wait_chunk_result().unwrap();
+
+        assert_eq!(generated.chunk.key, ChunkKey::new(100, 100));
+        assert_eq!(generated.epoch, 1);
+        assert!(world.get_chunk_result().is_none());
+    }
+
+    #[test]
+    fn tiles_above_every_threshold_are_the_peak() {
+        for (preset, peak) in [
+            (Preset::Default, TileKind::Snow),
+            (Preset::DesertWorld, TileKind::Mountain),
+        ] {
+            let mut definition = WorldDefinition::from(preset);
+
+            for (i, (_, threshold)) in definition.terrain.thresholds.iter_mut().enumerate() {
+                *threshold = -2.0 + i as f64 * 0.1;
+            }
+
+            let world = world_with(definition);
+            world.request_chunk(ChunkKey::new(0, 0));
+            let chunk = world.wait_chunk_result().unwrap().chunk;
+
+            assert!(chunk.tiles.iter().all(|&tile| tile == peak));
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::{Colour, Palette, TileKind};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    #[default]
+    Default,
+    Archipelago,
+    Pangaea,
+    DesertWorld,
+    IceAge,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Terrain {
+    /// Summed by weight into the elevation, in the order of `NoiseLayer::NAMES`.
+    pub layers: [NoiseLayer; 2],
+    /// Tiles paired with the elevation they extend up to, in ascending order. Anything
+    /// higher is `peak`.
+    pub thresholds: [(TileKind, f64); 4],
+    pub peak: TileKind,
+    pub palette: Palette,
+}
+
+/// One octave of Perlin noise.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NoiseLayer {
+    /// How far the noise moves per tile, so smaller steps give larger features.
+    pub step: f64,
+    /// Relative to the other layers' weights.
+    pub weight: u32,
+    /// Mixed with the world seed so each layer differs. Zero uses the world seed as is.
+    pub seed: u32,
+}
+
+impl Preset {
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Default => "Default",
+            Preset::Archipelago => "Archipelago",
+            Preset::Pangaea => "Pangaea",
+            Preset::DesertWorld => "Desert world",
+            Preset::IceAge => "Ice age",
+        }
+    }
+
+    /// The preset after this one, wrapping around to the first.
+    pub fn next(self) -> Self {
+        self.step(1)
+    }
+
+    /// The preset before this one, wrapping around to the last.
+    pub fn previous(self) -> Self {
+        self.step(Self::value_variants().len() - 1)
+    }
+
+    fn step(self, offset: usize) -> Self {
+        let presets = Self::value_variants();
+        let index = presets.iter().position(|&preset| preset == self).unwrap();
+        presets[(index + offset) % presets.len()]
+    }
+
+    pub fn terrain(self) -> Terrain {
+        let (base_step, detail_step, [ocean, beach, grassland, mountain], peak) = match self {
+            Preset::Default => (0.005, 0.02, [-0.1, -0.05, 0.45, 0.85], TileKind::Snow),
+            Preset::Archipelago => (0.012, 0.03, [0.25, 0.3, 0.6, 0.85], TileKind::Snow),
+            Preset::Pangaea => (0.002, 0.02, [-0.35, -0.3, 0.4, 0.8], TileKind::Snow),
+            Preset::DesertWorld => (0.005, 0.02, [-0.6, 0.45, 0.5, 0.9], TileKind::Mountain),
+            Preset::IceAge => (0.005, 0.02, [-0.3, -0.28, -0.1, 0.05], TileKind::Snow),
+        };
+
+        Terrain {
+            layers: [
+                NoiseLayer {
+                    step: base_step,
+                    weight: 4,
+                    seed: 0,
+                },
+                NoiseLayer {
+                    step: detail_step,
+                    weight: 1,
+                    seed: 1,
+                },
+            ],
+            thresholds: [
+                (TileKind::Ocean, ocean),
+                (TileKind::Beach, beach),
+                (TileKind::Grassland, grassland),
+                (TileKind::Mountain, mountain),
+            ],
+            peak,
+            palette: self.palette(),
+        }
+    }
+
+    fn palette(self) -> Palette {
+        let colours = match self {
+            Preset::Default => [
+                [0, 70, 170],
+                [190, 180, 130],
+                [20, 220, 100],
+                [180, 180, 180],
+                [220, 220, 220],
+            ],
+            Preset::Archipelago => [
+                [0, 130, 190],
+                [235, 220, 165],
+                [40, 190, 90],
+                [140, 135, 120],
+                [235, 235, 235],
+            ],
+            Preset::Pangaea => [
+                [0, 60, 140],
+                [180, 165, 110],
+                [70, 160, 60],
+                [130, 110, 90],
+                [225, 225, 225],
+            ],
+            Preset::DesertWorld => [
+                [40, 110, 150],
+                [235, 205, 140],
+                [205, 165, 95],
+                [160, 100, 60],
+                [240, 230, 210],
+            ],
+            Preset::IceAge => [
+                [30, 60, 110],
+                [165, 175, 185],
+                [150, 170, 160],
+                [190, 200, 215],
+                [245, 250, 255],
+            ],
+        };
+
+        Palette::new(colours.map(|[r, g, b]| Colour::new(r, g, b)))
+    }
+}
+
+impl NoiseLayer {
+    pub const NAMES: [&'static str; 2] = ["base", "detail"];
+}
@@ -0,0 +1,442 @@
+use std::{
+    collections::VecDeque,
+    sync::{Condvar, Mutex},
+    time::Duration,
+};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::coords::ChunkRange;
+
+use super::{task::Request, ChunkKey};
+
+/// How often a blocked request checks whether it should give up.
+const BLOCK_POLL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WhenFull {
+    /// Drop whichever request is least likely to be seen, which may be the new one: first
+    /// any from before the world was reconfigured, then any out of view, then whichever is
+    /// farthest from the centre of the view
+    #[default]
+    DropFarthest,
+    /// Turn the new request away
+    Reject,
+    /// Wait for a generation thread to take a request, unless generated chunks are
+    /// piling up uncollected
+    Block,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct QueueLimits {
+    pub requests: usize,
+    /// Generated chunks waiting to be collected, beyond which the generation threads wait.
+    pub results: usize,
+    pub when_full: WhenFull,
+}
+
+/// Where the view is, so a full queue can tell which requests matter most.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Focus {
+    pub centre: ChunkKey,
+    pub visible: ChunkRange,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Requested {
+    Queued,
+    /// The chunk was already waiting, so nothing more was queued.
+    Coalesced,
+    /// Queued, but the given chunk was dropped to make room.
+    Displaced(ChunkKey),
+    /// The queue was full, so the chunk will not be generated.
+    Rejected,
+}
+
+/// How many requests have not been generated as asked, since the world was created.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct QueueCounts {
+    pub coalesced: u64,
+    /// Displaced to make room, or cancelled.
+    pub dropped: u64,
+    pub rejected: u64,
+}
+
+/// Requests waiting for the generation threads, which at most one of is kept per chunk.
+pub struct RequestQueue {
+    state: Mutex<State>,
+    /// Signalled when a request is added, or the queue is closed.
+    added: Condvar,
+    /// Signalled when a request is taken.
+    taken: Condvar,
+    capacity: usize,
+}
+
+struct State {
+    requests: VecDeque<Request>,
+    counts: QueueCounts,
+    focus: Option<Focus>,
+    closed: bool,
+}
+
+impl Default for QueueLimits {
+    fn default() -> Self {
+        Self {
+            requests: 256,
+            results: 16,
+            when_full: WhenFull::default(),
+        }
+    }
+}
+
+impl RequestQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                requests: VecDeque::new(),
+                counts: QueueCounts::default(),
+                focus: None,
+                closed: false,
+            }),
+            added: Condvar::new(),
+            taken: Condvar::new(),
+            capacity,
+        }
+    }
+
+    /// Adds `request` unless its chunk is already waiting. A blocking push gives up once
+    /// `give_up` returns true.
+    pub fn push<F: Fn() -> bool>(
+        &self,
+        request: Request,
+        when_full: WhenFull,
+        give_up: F,
+    ) -> Requested {
+        let mut state = self.state.lock().unwrap();
+
+        if state
+            .requests
+            .iter()
+            .any(|queued| queued.key == request.key)
+        {
+            state.counts.coalesced += 1;
+            return Requested::Coalesced;
+        }
+
+        let mut requested = Requested::Queued;
+
+        if state.requests.len() >= self.capacity {
+            match when_full {
+                WhenFull::DropFarthest => match state.farthest(&request) {
+                    Some(index) => {
+                        let dropped = state.requests.remove(index).unwrap();
+                        state.counts.dropped += 1;
+                        requested = Requested::Displaced(dropped.key);
+                    }
+                    None => {
+                        state.counts.rejected += 1;
+                        return Requested::Rejected;
+                    }
+                },
+                WhenFull::Reject => {
+                    state.counts.rejected += 1;
+                    return Requested::Rejected;
+                }
+                WhenFull::Block => {
+                    while state.requests.len() >= self.capacity {
+                        if give_up() {
+                            state.counts.rejected += 1;
+                            return Requested::Rejected;
+                        }
+
+                        state = self.taken.wait_timeout(state, BLOCK_POLL).unwrap().0;
+                    }
+                }
+            }
+        }
+
+        state.requests.push_back(request);
+        self.added.notify_one();
+        requested
+    }
+
+    /// Waits for the oldest request, or `None` once the queue has been closed.
+    pub fn pop(&self) -> Option<Request> {
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            if state.closed {
+                return None;
+            }
+
+            if let Some(request) = state.requests.pop_front() {
+                self.taken.notify_one();
+                return Some(request);
+            }
+
+            state = self.added.wait(state).unwrap();
+        }
+    }
+
+    pub fn cancel(&self, key: ChunkKey) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let len = state.requests.len();
+
+        state.requests.retain(|request| request.key != key);
+
+        let cancelled = state.requests.len() < len;
+
+        if cancelled {
+            state.counts.dropped += 1;
+            self.taken.notify_one();
+        }
+
+        cancelled
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        state.counts.dropped += state.requests.len() as u64;
+        state.requests.clear();
+        self.taken.notify_all();
+    }
+
+    /// Moves the view that requests are dropped around once the queue is full.
+    pub fn set_focus(&self, focus: Focus) {
+        self.state.lock().unwrap().focus = Some(focus);
+    }
+
+    /// Stops the generation threads once they finish their current chunks.
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.added.notify_all();
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().requests.len()
+    }
+
+    pub fn counts(&self) -> QueueCounts {
+        self.state.lock().unwrap().counts
+    }
+}
+
+impl State {
+    /// The waiting request to drop in favour of `incoming`, or `None` if `incoming` should
+    /// be dropped instead. Without a view to go by, the oldest request goes.
+    fn farthest(&self, incoming: &Request) -> Option<usize> {
+        let older = |request: &Request| request.epoch < incoming.epoch;
+
+        let focus = match self.focus {
+            Some(focus) => focus,
+            None => return self.requests.iter().position(older).or(Some(0)),
+        };
+
+        let rank = |request: &Request| {
+            let (dx, dy) = (
+                request.key.x - focus.centre.x,
+                request.key.y - focus.centre.y,
+            );
+            (
+                older(request),
+                !focus.visible.contains(request.key),
+                dx * dx + dy * dy,
+            )
+        };
+
+        let (index, farthest) = self
+            .requests
+            .iter()
+            .map(rank)
+            .enumerate()
+            .max_by_key(|&(_, rank)| rank)?;
+
+        (farthest > rank(incoming)).then_some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, time::Instant};
+
+    use super::*;
+
+    fn request(x: i64, y: i64, epoch: u64) -> Request {
+        Request {
+            key: ChunkKey::new(x, y),
+            time: Instant::now(),
+            epoch,
+        }
+    }
+
+    fn push(queue: &RequestQueue, request: Request, when_full: WhenFull) -> Requested {
+        queue.push(request, when_full, || true)
+    }
+
+    /// A queue holding two requests, viewing the chunks around the origin.
+    fn full_queue(requests: [Request; 2]) -> RequestQueue {
+        let queue = RequestQueue::new(2);
+
+        queue.set_focus(Focus {
+            centre: ChunkKey::new(0, 0),
+            visible: ChunkRange {
+                min: ChunkKey::new(-2, -2),
+                max: ChunkKey::new(2, 2),
+            },
+        });
+
+        for request in requests {
+            assert_eq!(push(&queue, request, WhenFull::Reject), Requested::Queued);
+        }
+
+        queue
+    }
+
+    fn waiting(queue: &RequestQueue) -> Vec<ChunkKey> {
+        let state = queue.state.lock().unwrap();
+        state.requests.iter().map(|request| request.key).collect()
+    }
+
+    #[test]
+    fn requests_for_waiting_chunks_are_coalesced() {
+        let queue = RequestQueue::new(4);
+
+        assert_eq!(
+            push(&queue, request(0, 0, 0), WhenFull::Reject),
+            Requested::Queued
+        );
+        assert_eq!(
+            push(&queue, request(0, 0, 0), WhenFull::Reject),
+            Requested::Coalesced
+        );
+
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.counts().coalesced, 1);
+    }
+
+    #[test]
+    fn drop_farthest_drops_older_epochs_first() {
+        let queue = full_queue([request(0, 0, 0), request(1, 0, 1)]);
+
+        assert_eq!(
+            push(&queue, request(2, 2, 1), WhenFull::DropFarthest),
+            Requested::Displaced(ChunkKey::new(0, 0))
+        );
+        assert_eq!(waiting(&queue), [ChunkKey::new(1, 0), ChunkKey::new(2, 2)]);
+    }
+
+    #[test]
+    fn drop_farthest_drops_requests_out_of_view_first() {
+        // Off the edge of the view, though nearer the centre than the corner
+        let queue = full_queue([request(3, 0, 0), request(2, 2, 0)]);
+
+        assert_eq!(
+            push(&queue, request(1, 0, 0), WhenFull::DropFarthest),
+            Requested::Displaced(ChunkKey::new(3, 0))
+        );
+        assert_eq!(queue.counts().dropped, 1);
+    }
+
+    #[test]
+    fn drop_farthest_keeps_the_centre_of_the_view() {
+        let queue = full_queue([request(0, 0, 0), request(2, 0, 0)]);
+
+        assert_eq!(
+            push(&queue, request(0, 1, 0), WhenFull::DropFarthest),
+            Requested::Displaced(ChunkKey::new(2, 0))
+        );
+        assert_eq!(
+            push(&queue, request(0, -2, 0), WhenFull::DropFarthest),
+            Requested::Rejected
+        );
+
+        assert_eq!(waiting(&queue), [ChunkKey::new(0, 0), ChunkKey::new(0, 1)]);
+        assert_eq!(queue.counts().rejected, 1);
+    }
+
+    #[test]
+    fn drop_farthest_drops_the_oldest_without_a_view() {
+        let queue = RequestQueue::new(2);
+
+        for key in [(0, 0), (5, 5)] {
+            push(&queue, request(key.0, key.1, 0), WhenFull::DropFarthest);
+        }
+
+        assert_eq!(
+            push(&queue, request(9, 9, 0), WhenFull::DropFarthest),
+            Requested::Displaced(ChunkKey::new(0, 0))
+        );
+    }
+
+    #[test]
+    fn reject_turns_new_requests_away() {
+        let queue = full_queue([request(3, 3, 0), request(4, 4, 0)]);
+
+        assert_eq!(
+            push(&queue, request(0, 0, 0), WhenFull::Reject),
+            Requested::Rejected
+        );
+        assert_eq!(waiting(&queue), [ChunkKey::new(3, 3), ChunkKey::new(4, 4)]);
+        assert_eq!(queue.counts().rejected, 1);
+    }
+
+    #[test]
+    fn block_waits_until_it_gives_up() {
+        let queue = full_queue([request(0, 0, 0), request(1, 0, 0)]);
+        let checks = Cell::new(0);
+
+        let requested = queue.push(request(2, 0, 0), WhenFull::Block, || {
+            checks.set(checks.get() + 1);
+            checks.get() == 3
+        });
+
+        assert_eq!(requested, Requested::Rejected);
+        assert_eq!(checks.get(), 3);
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.counts().rejected, 1);
+    }
+
+    #[test]
+    fn block_queues_while_there_is_room() {
+        let queue = RequestQueue::new(2);
+
+        assert_eq!(
+            push(&queue, request(0, 0, 0), WhenFull::Block),
+            Requested::Queued
+        );
+        assert_eq!(queue.pop().unwrap().key, ChunkKey::new(0, 0));
+    }
+
+    #[test]
+    fn cancelled_requests_are_not_generated() {
+        let queue = full_queue([request(0, 0, 0), request(1, 0, 0)]);
+
+        assert!(queue.cancel(ChunkKey::new(0, 0)));
+        assert!(!queue.cancel(ChunkKey::new(0, 0)));
+
+        assert_eq!(queue.pop().unwrap().key, ChunkKey::new(1, 0));
+        assert_eq!(queue.counts().dropped, 1);
+    }
+
+    #[test]
+    fn clearing_counts_every_request_as_dropped() {
+        let queue = full_queue([request(0, 0, 0), request(1, 0, 0)]);
+
+        queue.clear();
+
+        assert_eq!(queue.len(), 0);
+        assert_eq!(queue.counts().dropped, 2);
+    }
+
+    #[test]
+    fn closing_stops_pop_with_requests_waiting() {
+        let queue = full_queue([request(0, 0, 0), request(1, 0, 0)]);
+
+        queue.close();
+
+        assert!(queue.pop().is_none());
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display, Formatter},
+    time::{Duration, Instant},
+};
+
+use super::QueueCounts;
+
+/// How many of the most recent chunks the latencies are taken over.
+const RECENT_CHUNKS: usize = 1024;
+
+/// How far back completed chunks count towards the throughput.
+const THROUGHPUT_PERIOD: Duration = Duration::from_secs(2);
+
+/// How long a chunk waited for a generation thread, and then took to generate.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ChunkTiming {
+    pub queued: Duration,
+    pub generation: Duration,
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Latency {
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct WorldStats {
+    pub generated: u64,
+    pub queued: usize,
+    /// Chunks generated but not yet collected.
+    pub ready: usize,
+    /// Requests coalesced, dropped or rejected rather than queued.
+    pub requests: QueueCounts,
+    pub generation: Latency,
+    pub queue_wait: Latency,
+    /// Chunks generated per second over the last couple of seconds.
+    pub throughput: f64,
+}
+
+/// Written by the generation threads as each chunk completes.
+#[derive(Default)]
+pub struct Recorder {
+    generated: u64,
+    recent: VecDeque<(Instant, ChunkTiming)>,
+}
+
+impl Latency {
+    /// Nearest-rank percentiles, or all zero if there are no samples.
+    pub fn new<I: IntoIterator<Item = Duration>>(samples: I) -> Self {
+        let mut samples = samples.into_iter().collect::<Vec<_>>();
+
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        samples.sort_unstable();
+
+        let percentile = |p: f64| {
+            let rank = (p * samples.len() as f64).ceil() as usize;
+            samples[rank.clamp(1, samples.len()) - 1]
+        };
+
+        Self {
+            mean: samples.iter().sum::<Duration>() / samples.len() as u32,
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+impl Display for Latency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+
+        write!(
+            f,
+            "mean {:.1} ms, p50 {:.1} ms, p90 {:.1} ms, p99 {:.1} ms, max {:.1} ms",
+            ms(self.mean),
+            ms(self.p50),
+            ms(self.p90),
+            ms(self.p99),
+            ms(self.max)
+        )
+    }
+}
+
+impl Recorder {
+    pub fn record(&mut self, finished: Instant, timing: ChunkTiming) {
+        if self.recent.len() == RECENT_CHUNKS {
+            self.recent.pop_front();
+        }
+
+        self.generated += 1;
+        self.recent.push_back((finished, timing));
+    }
+
+    pub fn stats(&self, queued: usize, ready: usize, requests: QueueCounts) -> WorldStats {
+        let since = Instant::now().checked_sub(THROUGHPUT_PERIOD);
+        let completed = self
+            .recent
+            .iter()
+            .filter(|&&(finished, _)| since.is_none_or(|since| finished >= since))
+            .count();
+
+        WorldStats {
+            generated: self.generated,
+            queued,
+            ready,
+            requests,
+            generation: Latency::new(self.recent.iter().map(|(_, timing)| timing.generation)),
+            queue_wait: Latency::new(self.recent.iter().map(|(_, timing)| timing.queued)),
+            throughput: completed as f64 / THROUGHPUT_PERIOD.as_secs_f64(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn no_samples_give_zero_latency() {
+        let latency = Latency::new([]);
+
+        assert_eq!(latency.mean, Duration::ZERO);
+        assert_eq!(latency.p50, Duration::ZERO);
+        assert_eq!(latency.max, Duration::ZERO);
+    }
+
+    #[test]
+    fn one_sample_is_every_percentile() {
+        let latency = Latency::new([ms(7)]);
+
+        for duration in [
+            latency.mean,
+            latency.p50,
+            latency.p90,
+            latency.p99,
+            latency.max,
+        ] {
+            assert_eq!(duration, ms(7));
+        }
+    }
+
+    #[test]
+    fn percentiles_take_the_nearest_rank() {
+        // Out of order, as chunks finish
+        let latency = Latency::new([10, 3, 7, 1, 9, 2, 8, 4, 6, 5].map(ms));
+
+        assert_eq!(latency.mean, Duration::from_micros(5500));
+        assert_eq!(latency.p50, ms(5));
+        assert_eq!(latency.p90, ms(9));
+        assert_eq!(latency.p99, ms(10));
+        assert_eq!(latency.max, ms(10));
+
+        let latency = Latency::new((1..=100).map(ms));
+
+        assert_eq!(latency.p50, ms(50));
+        assert_eq!(latency.p90, ms(90));
+        assert_eq!(latency.p99, ms(99));
+    }
+
+    #[test]
+    fn recorder_keeps_the_most_recent_chunks() {
+        let mut recorder = Recorder::default();
+        let now = Instant::now();
+
+        for millis in 0..RECENT_CHUNKS as u64 + 10 {
+            let timing = ChunkTiming {
+                queued: ms(1),
+                generation: ms(millis),
+            };
+            recorder.record(now, timing);
+        }
+
+        let stats = recorder.stats(3, 2, QueueCounts::default());
+
+        assert_eq!(stats.generated, RECENT_CHUNKS as u64 + 10);
+        assert_eq!((stats.queued, stats.ready), (3, 2));
+        assert_eq!(stats.generation.max, ms(RECENT_CHUNKS as u64 + 9));
+        assert_eq!(stats.generation.p50, ms(RECENT_CHUNKS as u64 / 2 + 9));
+        assert_eq!(stats.queue_wait.p99, ms(1));
+        assert_eq!(
+            stats.throughput,
+            RECENT_CHUNKS as f64 / THROUGHPUT_PERIOD.as_secs_f64()
+        );
+    }
+}
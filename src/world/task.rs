@@ -1,63 +1,201 @@
-use std::time::Instant;
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
-use crossbeam_channel::{Receiver, Sender};
+use crossbeam_channel::Sender;
+use tracing::{debug, debug_span, trace};
 use worldgen::{
     constraint,
     noise::perlin::PerlinNoise,
-    noisemap::{NoiseMap, NoiseMapGenerator, Seed, Step},
+    noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Seed, Step},
     world::{
         tile::{Constraint, ConstraintType},
         Size, Tile, World,
     },
 };
 
-use super::{Chunk, ChunkKey, Colour};
+use super::{
+    queue::RequestQueue,
+    stats::{ChunkTiming, Recorder},
+    Chunk, ChunkKey, GeneratedChunk, NoiseLayer, Terrain, TexelPool, TileKind, TileSample,
+    WorldConfig,
+};
+
+/// The noise maps the world is generated from, shared so tiles can be sampled outside
+/// the generation threads.
+#[derive(Copy, Clone)]
+pub struct Noise {
+    layers: [NoiseMap<PerlinNoise>; 2],
+    weights: [i64; 2],
+}
+
+/// A chunk to generate, when it was asked for, and the epoch it was asked for in.
+pub struct Request {
+    pub key: ChunkKey,
+    pub time: Instant,
+    pub epoch: u64,
+}
+
+/// What the world is currently generated from. Replaced as a whole when the world is
+/// reconfigured, with a new epoch so that older requests and results can be told apart.
+#[derive(Copy, Clone)]
+pub struct Params {
+    pub epoch: u64,
+    pub terrain: Terrain,
+    pub noise: Noise,
+}
+
+/// Where the generation threads send their results, and what they write them with.
+pub struct Output<P: TexelPool> {
+    pub tx: Sender<GeneratedChunk<P::Buffer>>,
+    pub recorder: Arc<Mutex<Recorder>>,
+    pub pool: P,
+    /// Called after each result is sent.
+    pub notify: Box<dyn Fn() + Send + Sync>,
+}
 
 pub struct Worldgen {
-    world: World<Colour>,
+    world: World<TileKind>,
+}
+
+impl Noise {
+    pub fn new(seed: u64, terrain: &Terrain) -> Self {
+        let noise = PerlinNoise::new();
+
+        // Each layer needs its own seed, but they must all follow from the world seed
+        let layer = |layer: &NoiseLayer| {
+            let layer_seed = match layer.seed {
+                0 => Seed::of_value(seed),
+                salt => Seed::of((seed, salt)),
+            };
+
+            NoiseMap::new(noise)
+                .set(layer_seed)
+                .set(Step::of(layer.step, layer.step))
+        };
+
+        Self {
+            layers: terrain.layers.each_ref().map(layer),
+            weights: terrain.layers.map(|layer| layer.weight as i64),
+        }
+    }
+
+    fn elevation(&self) -> impl NoiseMapGenerator + 'static {
+        let [base, detail] = self.layers;
+        base * self.weights[0] + detail * self.weights[1]
+    }
+
+    pub fn sample(&self, x: i64, y: i64) -> TileSample {
+        // A one tile chunk has the same coordinates as the tile itself
+        let sample =
+            |nm: &dyn NoiseMapGeneratorBase| nm.generate_sized_chunk(Size::of(1, 1), x, y)[0][0];
+
+        TileSample {
+            elevation: sample(&self.elevation()),
+            layers: self.layers.iter().map(|layer| sample(layer)).collect(),
+        }
+    }
 }
 
 impl Worldgen {
-    pub fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<Colour>> {
+    pub fn new(config: &WorldConfig, params: &Params) -> Self {
+        let nm = Box::new(params.noise.elevation());
+
+        let world = params.terrain.thresholds.iter().fold(
+            World::new().set(Size::of(
+                config.chunk_size() as i64,
+                config.chunk_size() as i64,
+            )),
+            |world, &(kind, threshold)| {
+                world.add(Tile::new(kind).when(constraint!(nm.clone(), < threshold)))
+            },
+        );
+
+        Self {
+            world: world.add(Tile::new(params.terrain.peak)),
+        }
+    }
+
+    pub fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<TileKind>> {
         self.world.generate(x, y).unwrap()
     }
 }
 
-unsafe impl Sync for Worldgen {}
+pub fn worldgen_task<P: TexelPool>(
+    config: WorldConfig,
+    workers: usize,
+    requests: &RequestQueue,
+    params: &Mutex<Params>,
+    output: Output<P>,
+) {
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let thread_output = &output;
 
-pub fn worldgen_task(rx: Receiver<ChunkKey>, tx: Sender<Chunk>) {
-    let noise = PerlinNoise::new();
+            scope.spawn(move || {
+                // Each thread builds its own generator, again whenever the world is
+                // reconfigured
+                let mut current: Option<(u64, Worldgen)> = None;
 
-    let nm1 = NoiseMap::new(noise)
-        .set(Seed::of(Instant::now()))
-        .set(Step::of(0.005, 0.005));
+                while let Some(Request { key, time, epoch }) = requests.pop() {
+                    let params = *params.lock().unwrap();
 
-    let nm2 = NoiseMap::new(noise)
-        .set(Seed::of(Instant::now()))
-        .set(Step::of(0.02, 0.02));
+                    if params.epoch != epoch {
+                        trace!(x = key.x, y = key.y, "Skipping chunk from an older epoch");
+                        continue;
+                    }
 
-    let nm = Box::new(nm1 * 4 + nm2);
+                    if current
+                        .as_ref()
+                        .is_none_or(|&(current, _)| current != epoch)
+                    {
+                        current = Some((epoch, Worldgen::new(&config, &params)));
+                    }
 
-    let worldgen = Worldgen {
-        world: World::new()
-            .set(Size::of(512, 512))
-            .add(Tile::new(Colour::new(0, 70, 170)).when(constraint!(nm.clone(), < -0.1)))
-            .add(Tile::new(Colour::new(190, 180, 130)).when(constraint!(nm.clone(), < -0.05)))
-            .add(Tile::new(Colour::new(20, 220, 100)).when(constraint!(nm.clone(), < 0.45)))
-            .add(Tile::new(Colour::new(180, 180, 180)).when(constraint!(nm, < 0.85)))
-            .add(Tile::new(Colour::new(220, 220, 220))),
-    };
+                    let (_, worldgen) = current.as_ref().unwrap();
+                    let _span = debug_span!("generate_chunk", x = key.x, y = key.y).entered();
+                    let start = Instant::now();
+                    trace!("Generating chunk");
 
-    std::thread::scope(|scope| {
-        for _ in 0..12 {
-            let thread_rx = rx.clone();
-            let thread_tx = tx.clone();
-            let thread_wg = &worldgen;
+                    let data = worldgen.generate_chunk(key.x, key.y);
 
-            scope.spawn(move || {
-                while let Ok(key) = thread_rx.recv() {
-                    let data = thread_wg.generate_chunk(key.x, key.y);
-                    thread_tx.send(Chunk::new(key, data)).unwrap()
+                    let finished = Instant::now();
+                    let timing = ChunkTiming {
+                        queued: start.saturating_duration_since(time),
+                        generation: finished - start,
+                    };
+
+                    debug!(
+                        duration_ms = timing.generation.as_secs_f64() * 1000.0,
+                        queued_ms = timing.queued.as_secs_f64() * 1000.0,
+                        "Generated chunk"
+                    );
+
+                    thread_output
+                        .recorder
+                        .lock()
+                        .unwrap()
+                        .record(finished, timing);
+
+                    let chunk = Chunk::new(key, data, timing);
+                    let texels =
+                        chunk.texels(&config, &params.terrain.palette, &thread_output.pool);
+                    // Waits while the result queue is full, and stops if the world has
+                    // gone
+                    if thread_output
+                        .tx
+                        .send(GeneratedChunk {
+                            chunk,
+                            texels,
+                            epoch,
+                        })
+                        .is_err()
+                    {
+                        break;
+                    }
+
+                    (thread_output.notify)();
                 }
             });
         }
@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+/// Somewhere for the generation threads to write chunk textures, so that they reach the
+/// renderer as finished buffers rather than being built on the main thread.
+pub trait TexelPool: Send + Sync + 'static {
+    type Buffer: Send + 'static;
+
+    /// A buffer of exactly `len` bytes, every one of which is written by `fill`.
+    fn fill<F: FnOnce(&mut [u8])>(&self, len: usize, fill: F) -> Self::Buffer;
+}
+
+/// Plain heap allocations, for when the textures are not going to the GPU.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct HeapTexels;
+
+pub struct ChunkTexels<B> {
+    pub texture: B,
+    pub thumbnail: B,
+}
+
+impl TexelPool for HeapTexels {
+    type Buffer = Vec<u8>;
+
+    fn fill<F: FnOnce(&mut [u8])>(&self, len: usize, fill: F) -> Vec<u8> {
+        let mut buffer = vec![0; len];
+        fill(&mut buffer);
+        buffer
+    }
+}
+
+impl<P: TexelPool> TexelPool for Arc<P> {
+    type Buffer = P::Buffer;
+
+    fn fill<F: FnOnce(&mut [u8])>(&self, len: usize, fill: F) -> P::Buffer {
+        (**self).fill(len, fill)
+    }
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TileKind {
+    Ocean,
+    Beach,
+    Grassland,
+    Mountain,
+    Snow,
+}
+
+#[derive(Debug, Clone)]
+pub struct TileSample {
+    pub elevation: f64,
+    pub layers: Vec<f64>,
+}
+
+impl TileKind {
+    pub const COUNT: usize = 5;
+
+    /// From the lowest elevation to the highest.
+    pub const ALL: [TileKind; Self::COUNT] = [
+        TileKind::Ocean,
+        TileKind::Beach,
+        TileKind::Grassland,
+        TileKind::Mountain,
+        TileKind::Snow,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TileKind::Ocean => "Ocean",
+            TileKind::Beach => "Beach",
+            TileKind::Grassland => "Grassland",
+            TileKind::Mountain => "Mountain",
+            TileKind::Snow => "Snow",
+        }
+    }
+}